@@ -0,0 +1,478 @@
+use {
+    anchor_lang::{
+        prelude::*,
+        system_program,
+    },
+    anchor_spl::{
+        associated_token,
+        token,
+    },
+    crate::mint::{
+        apply_soft_cap,
+        mint_token,
+        next_vault,
+        CollectionPda,
+        ErrorCode,
+        MintBitmap,
+        MintMode,
+        MintTokenAccounts,
+        NftPda,
+        TokenRecord,
+        WalletMints,
+    },
+};
+
+pub fn create_auction(
+    ctx: Context<CreateAuction>,
+    token_id: u64,
+    reserve_price: u64,
+    min_increment: u64,
+    end_ts: i64,
+) -> Result<()> {
+    let nft_pda = &ctx.accounts.nft_pda;
+
+    if &nft_pda.creator != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    // other modes assign ids themselves and would run into the reserved one
+    if nft_pda.mint_mode != MintMode::Explicit {
+        return Err(error!(ErrorCode::InvalidMintMode));
+    }
+
+    if token_id == 0 || token_id > nft_pda.max_supply {
+        return Err(error!(ErrorCode::InvalidTokenId));
+    }
+
+    if min_increment == 0 || end_ts <= Clock::get()?.unix_timestamp {
+        return Err(error!(ErrorCode::InvalidAuction));
+    }
+
+    {
+        // reserve the token id so it can't be minted outside the auction
+        let mint_bitmap = ctx.accounts.mint_bitmap.to_account_info();
+        let mut data = mint_bitmap.try_borrow_mut_data()?;
+        let bits = &mut data[MintBitmap::BITS_OFFSET..];
+
+        if MintBitmap::is_minted(bits, token_id)? {
+            return Err(error!(ErrorCode::TokenAlreadyMinted));
+        }
+
+        MintBitmap::set_minted(bits, token_id)?;
+    }
+
+    let auction = &mut ctx.accounts.auction;
+
+    auction.token_id = token_id;
+    auction.reserve_price = reserve_price;
+    auction.min_increment = min_increment;
+    auction.end_ts = end_ts;
+    auction.highest_bidder = Pubkey::default();
+    auction.highest_bid = 0;
+    auction.settled = false;
+    auction.bump = *ctx.bumps.get("auction").unwrap();
+
+    ctx.accounts.nft_pda.open_auctions += 1;
+
+    Ok(())
+}
+
+pub fn place_bid(ctx: Context<PlaceBid>, _token_id: u64, amount: u64) -> Result<()> {
+    if ctx.accounts.nft_pda.paused {
+        return Err(error!(ErrorCode::SalePaused));
+    }
+
+    let auction = &ctx.accounts.auction;
+
+    if Clock::get()?.unix_timestamp >= auction.end_ts {
+        return Err(error!(ErrorCode::AuctionEnded));
+    }
+
+    let min_bid = if auction.has_bids() {
+        auction.highest_bid.saturating_add(auction.min_increment)
+    } else {
+        auction.reserve_price
+    };
+
+    if amount < min_bid {
+        return Err(error!(ErrorCode::BidTooLow));
+    }
+
+    if auction.has_bids() && &auction.highest_bidder != ctx.accounts.previous_bidder.key {
+        return Err(error!(ErrorCode::InvalidBidder));
+    }
+
+    msg!("Escrowing bid of {} lamports...", amount);
+    system_program::transfer(
+        CpiContext::new(
+            ctx.accounts.system_program.to_account_info(),
+            system_program::Transfer {
+                from: ctx.accounts.bidder.to_account_info(),
+                to: auction.to_account_info(),
+            }
+        ),
+        amount
+    )?;
+
+    if auction.has_bids() {
+        msg!("Refunding {} lamports to the previous bidder...", auction.highest_bid);
+        **auction.to_account_info().try_borrow_mut_lamports()? -= auction.highest_bid;
+        **ctx.accounts.previous_bidder.try_borrow_mut_lamports()? += auction.highest_bid;
+    }
+
+    let auction = &mut ctx.accounts.auction;
+
+    auction.highest_bidder = ctx.accounts.bidder.key();
+    auction.highest_bid = amount;
+
+    Ok(())
+}
+
+/// While a soft cap is set, takes the escrow vault as the only remaining
+/// account.
+pub fn settle_auction<'info>(
+    ctx: Context<'_, '_, '_, 'info, SettleAuction<'info>>,
+    token_id: u64,
+) -> Result<()> {
+    if ctx.accounts.nft_pda.paused {
+        return Err(error!(ErrorCode::SalePaused));
+    }
+
+    let auction = &ctx.accounts.auction;
+
+    if auction.settled {
+        return Err(error!(ErrorCode::AuctionSettled));
+    }
+
+    if Clock::get()?.unix_timestamp < auction.end_ts {
+        return Err(error!(ErrorCode::AuctionNotEnded));
+    }
+
+    if !auction.has_bids() || &auction.highest_bidder != ctx.accounts.winner.key {
+        return Err(error!(ErrorCode::InvalidBidder));
+    }
+
+    let nft_pda = &ctx.accounts.nft_pda;
+
+    if &ctx.accounts.collection_pda.mint != ctx.accounts.collection_mint.key {
+        return Err(error!(ErrorCode::InvalidCollectionMint));
+    }
+
+    // the winning bid is escrowed like any other payment while a soft cap is set
+    apply_soft_cap(nft_pda, auction.highest_bid, &mut ctx.accounts.wallet_mints)?;
+
+    let destination = if nft_pda.soft_cap > 0 {
+        next_vault(ctx.accounts.nft_manager.key, &mut ctx.remaining_accounts.iter())?
+    } else {
+        nft_pda.to_account_info()
+    };
+
+    mint_token(&ctx.accounts.mint_token_accounts(), token_id)?;
+
+    msg!("Releasing {} lamports to the treasury...", auction.highest_bid);
+    **auction.to_account_info().try_borrow_mut_lamports()? -= auction.highest_bid;
+    **destination.try_borrow_mut_lamports()? += auction.highest_bid;
+
+    let token_record = &mut ctx.accounts.token_record;
+
+    token_record.token_id = token_id;
+    token_record.mint = ctx.accounts.mint.key();
+    token_record.owner = ctx.accounts.winner.key();
+    token_record.slot = Clock::get()?.slot;
+    token_record.bump = *ctx.bumps.get("token_record").unwrap();
+
+    let highest_bid = ctx.accounts.auction.highest_bid;
+
+    ctx.accounts.nft_pda.record_mint(highest_bid);
+    ctx.accounts.nft_pda.open_auctions -= 1;
+    ctx.accounts.auction.settled = true;
+
+    msg!("Auction settled.");
+
+    Ok(())
+}
+
+pub fn cancel_auction(ctx: Context<CancelAuction>, token_id: u64) -> Result<()> {
+    let nft_pda = &ctx.accounts.nft_pda;
+
+    if &nft_pda.creator != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    if ctx.accounts.auction.has_bids() {
+        return Err(error!(ErrorCode::AuctionHasBids));
+    }
+
+    {
+        // release the reserved token id
+        let mint_bitmap = ctx.accounts.mint_bitmap.to_account_info();
+        let mut data = mint_bitmap.try_borrow_mut_data()?;
+
+        MintBitmap::clear_minted(&mut data[MintBitmap::BITS_OFFSET..], token_id)?;
+    }
+
+    ctx.accounts.nft_pda.open_auctions -= 1;
+
+    Ok(())
+}
+
+/// Returns the winning bid if the auction was not settled within
+/// `Auction::SETTLEMENT_WINDOW` of its end, e.g. because the sale stayed
+/// paused.
+pub fn refund_auction(ctx: Context<RefundAuction>, token_id: u64) -> Result<()> {
+    let auction = &ctx.accounts.auction;
+
+    if auction.settled {
+        return Err(error!(ErrorCode::AuctionSettled));
+    }
+
+    if !auction.has_bids() || &auction.highest_bidder != ctx.accounts.bidder.key {
+        return Err(error!(ErrorCode::InvalidBidder));
+    }
+
+    if Clock::get()?.unix_timestamp < auction.end_ts.saturating_add(Auction::SETTLEMENT_WINDOW) {
+        return Err(error!(ErrorCode::SettlementWindowOpen));
+    }
+
+    msg!("Refunding {} lamports to the highest bidder...", auction.highest_bid);
+    **auction.to_account_info().try_borrow_mut_lamports()? -= auction.highest_bid;
+    **ctx.accounts.bidder.try_borrow_mut_lamports()? += auction.highest_bid;
+
+    {
+        // release the reserved token id
+        let mint_bitmap = ctx.accounts.mint_bitmap.to_account_info();
+        let mut data = mint_bitmap.try_borrow_mut_data()?;
+
+        MintBitmap::clear_minted(&mut data[MintBitmap::BITS_OFFSET..], token_id)?;
+    }
+
+    ctx.accounts.nft_pda.open_auctions -= 1;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(token_id: u64)]
+pub struct CreateAuction<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut, seeds = [b"mint_bitmap".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub mint_bitmap: AccountLoader<'info, MintBitmap>,
+    // space: 8 discriminator
+    // + 8 token_id
+    // + 8 reserve_price
+    // + 8 min_increment
+    // + 8 end_ts
+    // + 32 highest_bidder
+    // + 8 highest_bid
+    // + 1 settled
+    // + 1 bump
+    #[account(
+        init,
+        payer = authority,
+        space = 82,
+        seeds = [b"auction".as_ref(), nft_manager.to_account_info().key.as_ref(), token_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_id: u64)]
+pub struct PlaceBid<'info> {
+    #[account(seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(
+        mut,
+        seeds = [b"auction".as_ref(), nft_manager.to_account_info().key.as_ref(), token_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+    /// CHECK: Checked against the auction's highest bidder; any account before the first bid
+    #[account(mut)]
+    pub previous_bidder: UncheckedAccount<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_id: u64)]
+pub struct SettleAuction<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut, seeds = [b"collection_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub collection_pda: Account<'info, CollectionPda>,
+    #[account(
+        mut,
+        close = creator,
+        seeds = [b"auction".as_ref(), nft_manager.to_account_info().key.as_ref(), token_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub auction: Account<'info, Auction>,
+    // space: 8 discriminator
+    // + 8 token_id
+    // + 32 mint
+    // + 32 owner
+    // + 8 slot
+    // + 1 bump
+    #[account(
+        init,
+        payer = payer,
+        space = 89,
+        seeds = [b"token_record".as_ref(), nft_manager.to_account_info().key.as_ref(), token_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub token_record: Account<'info, TokenRecord>,
+    // space: 8 discriminator
+    // + 2 * 16 counts
+    // + 2 allowlist_count
+    // + 8 escrowed_lamports
+    // + 2 escrowed_count
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 52,
+        seeds = [b"wallet_mints".as_ref(), nft_manager.to_account_info().key.as_ref(), winner.key.as_ref()],
+        bump,
+    )]
+    pub wallet_mints: Account<'info, WalletMints>,
+    /// CHECK: We're about to create this with Metaplex
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: We're about to create this with Metaplex
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub mint: Signer<'info>,
+    /// CHECK: We're about to create this with Anchor
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+    /// CHECK: We're about to create this with Anchor
+    #[account(mut)]
+    pub mint_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Checked against the auction's highest bidder
+    pub winner: UncheckedAccount<'info>,
+    /// CHECK: Receives the auction account's rent
+    #[account(mut, address = nft_pda.creator)]
+    pub creator: UncheckedAccount<'info>,
+    /// CHECK: We're about to create this with Anchor
+    #[account(mut)]
+    pub nft_manager: UncheckedAccount<'info>,
+    /// CHECK: We're about to create this with Anchor
+    pub collection_mint: UncheckedAccount<'info>,
+    /// CHECK: We're about to create this with Metaplex
+    #[account(mut)]
+    pub collection_metadata: UncheckedAccount<'info>,
+    /// CHECK: We're about to create this with Metaplex
+    #[account(mut)]
+    pub collection_master_edition: UncheckedAccount<'info>,
+    /// CHECK: We're about to create this with Metaplex
+    pub collection_authority_record: UncheckedAccount<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, token::Token>,
+    pub associated_token_program: Program<'info, associated_token::AssociatedToken>,
+    /// CHECK: Metaplex will check this
+    pub token_metadata_program: UncheckedAccount<'info>,
+}
+
+impl<'info> SettleAuction<'info> {
+    fn mint_token_accounts(&self) -> MintTokenAccounts<'_, 'info> {
+        MintTokenAccounts {
+            nft_pda: &self.nft_pda,
+            collection_pda: &self.collection_pda,
+            metadata: self.metadata.to_account_info(),
+            master_edition: self.master_edition.to_account_info(),
+            mint: self.mint.to_account_info(),
+            token_account: self.token_account.to_account_info(),
+            mint_authority: self.mint_authority.to_account_info(),
+            payer: self.payer.to_account_info(),
+            owner: self.winner.to_account_info(),
+            nft_manager: self.nft_manager.to_account_info(),
+            collection_mint: self.collection_mint.to_account_info(),
+            collection_metadata: self.collection_metadata.to_account_info(),
+            collection_master_edition: self.collection_master_edition.to_account_info(),
+            collection_authority_record: self.collection_authority_record.to_account_info(),
+            rent: self.rent.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            associated_token_program: self.associated_token_program.to_account_info(),
+        }
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(token_id: u64)]
+pub struct CancelAuction<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut, seeds = [b"mint_bitmap".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub mint_bitmap: AccountLoader<'info, MintBitmap>,
+    #[account(
+        mut,
+        close = authority,
+        seeds = [b"auction".as_ref(), nft_manager.to_account_info().key.as_ref(), token_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_id: u64)]
+pub struct RefundAuction<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut, seeds = [b"mint_bitmap".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub mint_bitmap: AccountLoader<'info, MintBitmap>,
+    #[account(
+        mut,
+        close = creator,
+        seeds = [b"auction".as_ref(), nft_manager.to_account_info().key.as_ref(), token_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub auction: Account<'info, Auction>,
+    #[account(mut)]
+    pub bidder: Signer<'info>,
+    /// CHECK: Receives the auction account's rent
+    #[account(mut, address = nft_pda.creator)]
+    pub creator: UncheckedAccount<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+/// An English auction for a single token id. Bids are escrowed in the
+/// auction account itself until they are outbid or settled.
+#[account]
+pub struct Auction {
+    pub token_id: u64,
+    pub reserve_price: u64,
+    pub min_increment: u64,
+    pub end_ts: i64,
+    pub highest_bidder: Pubkey,
+    pub highest_bid: u64,
+    pub settled: bool,
+    pub bump: u8,
+}
+
+impl Auction {
+    /// Seconds after `end_ts` during which only settlement is possible.
+    pub const SETTLEMENT_WINDOW: i64 = 7 * 24 * 60 * 60;
+
+    pub fn has_bids(&self) -> bool {
+        self.highest_bidder != Pubkey::default()
+    }
+}
@@ -1,8 +1,12 @@
 use anchor_lang::prelude::*;
 
+pub mod auction;
 pub mod mint;
+pub mod multisig;
 
+use auction::*;
 use mint::*;
+use multisig::*;
 
 declare_id!("As35BqTErxt7neUhzZik8P194q9zdFJmuzcLYu1BvpNh");
 
@@ -16,6 +20,7 @@ pub mod DOG {
         symbol: String,
         base_token_uri: String,
         price_lamports: u64,
+        max_supply: u64,
     ) -> Result<()> {
         mint::initialize(
             ctx,
@@ -23,9 +28,18 @@ pub mod DOG {
             symbol, 
             base_token_uri,
             price_lamports,
+            max_supply,
         )
     }
 
+    pub fn migrate_nft_pda(
+        ctx: Context<MigrateNftPda>,
+        max_supply: u64,
+        minted_token_ids: Vec<u64>,
+    ) -> Result<()> {
+        mint::migrate_nft_pda(ctx, max_supply, minted_token_ids)
+    }
+
     pub fn set_metadata(
         ctx: Context<SetMetadata>,
         name: String,
@@ -39,17 +53,206 @@ pub mod DOG {
         mint::set_price(ctx, price_lamports)
     }
 
+    pub fn set_price_overrides(ctx: Context<SetPriceOverrides>, price_overrides: Vec<PriceOverride>) -> Result<()> {
+        mint::set_price_overrides(ctx, price_overrides)
+    }
+
+    pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
+        mint::withdraw(ctx, amount)
+    }
+
+    pub fn set_revenue_split(ctx: Context<SetRevenueSplit>, payees: Vec<Payee>) -> Result<()> {
+        mint::set_revenue_split(ctx, payees)
+    }
+
+    pub fn distribute<'info>(ctx: Context<'_, '_, '_, 'info, Distribute<'info>>) -> Result<()> {
+        mint::distribute(ctx)
+    }
+
+    pub fn set_payment_mint(ctx: Context<SetPaymentMint>, price_tokens: u64) -> Result<()> {
+        mint::set_payment_mint(ctx, price_tokens)
+    }
+
+    pub fn clear_payment_mint(ctx: Context<ClearPaymentMint>) -> Result<()> {
+        mint::clear_payment_mint(ctx)
+    }
+
+    pub fn set_redemption(ctx: Context<SetRedemption>, redemption: Option<Redemption>) -> Result<()> {
+        mint::set_redemption(ctx, redemption)
+    }
+
+    pub fn withdraw_tokens(ctx: Context<WithdrawTokens>, amount: u64) -> Result<()> {
+        mint::withdraw_tokens(ctx, amount)
+    }
+
+    pub fn distribute_tokens<'info>(ctx: Context<'_, '_, '_, 'info, DistributeTokens<'info>>) -> Result<()> {
+        mint::distribute_tokens(ctx)
+    }
+
+    pub fn set_pricing_mode(ctx: Context<SetPricingMode>, pricing_mode: PricingMode) -> Result<()> {
+        mint::set_pricing_mode(ctx, pricing_mode)
+    }
+
+    pub fn set_soft_cap(ctx: Context<SetSoftCap>, soft_cap: u64, deadline: i64) -> Result<()> {
+        mint::set_soft_cap(ctx, soft_cap, deadline)
+    }
+
+    pub fn release_escrow(ctx: Context<ReleaseEscrow>, amount: u64) -> Result<()> {
+        mint::release_escrow(ctx, amount)
+    }
+
+    pub fn refund(ctx: Context<Refund>, token_id: u64) -> Result<()> {
+        mint::refund(ctx, token_id)
+    }
+
+    pub fn pause(ctx: Context<Pause>) -> Result<()> {
+        mint::pause(ctx)
+    }
+
+    pub fn unpause(ctx: Context<Unpause>) -> Result<()> {
+        mint::unpause(ctx)
+    }
+
+    pub fn set_role(ctx: Context<SetRole>, role: Role, admin: Pubkey) -> Result<()> {
+        mint::set_role(ctx, role, admin)
+    }
+
+    pub fn propose_manager(ctx: Context<ProposeManager>, new_manager: Pubkey) -> Result<()> {
+        mint::propose_manager(ctx, new_manager)
+    }
+
+    pub fn cancel_manager_proposal(ctx: Context<CancelManagerProposal>) -> Result<()> {
+        mint::cancel_manager_proposal(ctx)
+    }
+
+    pub fn accept_manager(ctx: Context<AcceptManager>) -> Result<()> {
+        mint::accept_manager(ctx)
+    }
+
+    pub fn set_max_supply(ctx: Context<SetMaxSupply>, max_supply: u64) -> Result<()> {
+        mint::set_max_supply(ctx, max_supply)
+    }
+
+    pub fn set_mint_mode(ctx: Context<SetMintMode>, mint_mode: MintMode) -> Result<()> {
+        mint::set_mint_mode(ctx, mint_mode)
+    }
+
+    pub fn set_allowlist_root(ctx: Context<SetAllowlistRoot>, allowlist_root: Option<[u8; 32]>) -> Result<()> {
+        mint::set_allowlist_root(ctx, allowlist_root)
+    }
+
+    pub fn set_gate_collection(ctx: Context<SetGateCollection>, gate_collection: Option<Pubkey>) -> Result<()> {
+        mint::set_gate_collection(ctx, gate_collection)
+    }
+
+    pub fn set_voucher_signer(ctx: Context<SetVoucherSigner>, voucher_signer: Option<Pubkey>) -> Result<()> {
+        mint::set_voucher_signer(ctx, voucher_signer)
+    }
+
+    pub fn set_referral_bps(ctx: Context<SetReferralBps>, referral_bps: u16) -> Result<()> {
+        mint::set_referral_bps(ctx, referral_bps)
+    }
+
+    pub fn register_referrer(ctx: Context<RegisterReferrer>) -> Result<()> {
+        mint::register_referrer(ctx)
+    }
+
+    pub fn add_sale_phase(
+        ctx: Context<AddSalePhase>,
+        start_ts: i64,
+        end_ts: i64,
+        price: u64,
+        per_wallet_cap: u16,
+    ) -> Result<()> {
+        mint::add_sale_phase(ctx, start_ts, end_ts, price, per_wallet_cap)
+    }
+
+    pub fn edit_sale_phase(
+        ctx: Context<EditSalePhase>,
+        phase_id: u8,
+        start_ts: i64,
+        end_ts: i64,
+        price: u64,
+        per_wallet_cap: u16,
+    ) -> Result<()> {
+        mint::edit_sale_phase(ctx, phase_id, start_ts, end_ts, price, per_wallet_cap)
+    }
+
+    pub fn remove_sale_phase(ctx: Context<RemoveSalePhase>, phase_id: u8) -> Result<()> {
+        mint::remove_sale_phase(ctx, phase_id)
+    }
+
+    pub fn free_token_ids(
+        ctx: Context<FreeTokenIds>,
+        start: u64,
+        limit: u8,
+    ) -> Result<Vec<u64>> {
+        mint::free_token_ids(ctx, start, limit)
+    }
+
     pub fn mint_collection(
         ctx: Context<MintCollection>, 
     ) -> Result<()> {
         mint::mint_collection(ctx)
     }
 
-    pub fn mint(
-        ctx: Context<MintNft>, 
+    pub fn mint<'info>(
+        ctx: Context<'_, '_, '_, 'info, MintNft<'info>>, 
         token_id: u64,
+        proof: Vec<[u8; 32]>,
+        allocation: u16,
+        voucher: Option<MintVoucher>,
+        max_price: u64,
+    ) -> Result<u64> {
+        mint::mint(ctx, token_id, proof, allocation, voucher, max_price)
+    }
+
+    pub fn initialize_remaining_token_ids(ctx: Context<InitializeRemainingTokenIds>) -> Result<()> {
+        mint::initialize_remaining_token_ids(ctx)
+    }
+
+    pub fn grow_remaining_token_ids(ctx: Context<GrowRemainingTokenIds>) -> Result<()> {
+        mint::grow_remaining_token_ids(ctx)
+    }
+
+    pub fn mint_random<'info>(
+        ctx: Context<'_, '_, '_, 'info, MintRandomNft<'info>>,
+        proof: Vec<[u8; 32]>,
+        allocation: u16,
+        voucher: Option<MintVoucher>,
+        max_price: u64,
     ) -> Result<()> {
-        mint::mint(ctx, token_id)
+        mint::mint_random(ctx, proof, allocation, voucher, max_price)
+    }
+
+    pub fn reveal_random_mint(ctx: Context<RevealRandomMint>, ticket: u64) -> Result<()> {
+        mint::reveal_random_mint(ctx, ticket)
+    }
+
+    pub fn create_auction(
+        ctx: Context<CreateAuction>,
+        token_id: u64,
+        reserve_price: u64,
+        min_increment: u64,
+        end_ts: i64,
+    ) -> Result<()> {
+        auction::create_auction(ctx, token_id, reserve_price, min_increment, end_ts)
+    }
+
+    pub fn place_bid(ctx: Context<PlaceBid>, token_id: u64, amount: u64) -> Result<()> {
+        auction::place_bid(ctx, token_id, amount)
+    }
+
+    pub fn settle_auction<'info>(ctx: Context<'_, '_, '_, 'info, SettleAuction<'info>>, token_id: u64) -> Result<()> {
+        auction::settle_auction(ctx, token_id)
+    }
+
+    pub fn cancel_auction(ctx: Context<CancelAuction>, token_id: u64) -> Result<()> {
+        auction::cancel_auction(ctx, token_id)
+    }
+
+    pub fn refund_auction(ctx: Context<RefundAuction>, token_id: u64) -> Result<()> {
+        auction::refund_auction(ctx, token_id)
     }
 
     pub fn set_collection(
@@ -72,4 +275,28 @@ pub mod DOG {
     ) -> Result<()> {
         mint::update_metadata_account(ctx, name, symbol, uri)
     }
+
+    pub fn create_multisig(
+        ctx: Context<CreateMultisig>,
+        signers: Vec<Pubkey>,
+        threshold: u8,
+        proposal_lifetime: i64,
+    ) -> Result<()> {
+        multisig::create_multisig(ctx, signers, threshold, proposal_lifetime)
+    }
+
+    pub fn propose_action(ctx: Context<ProposeAction>, action: AdminAction) -> Result<()> {
+        multisig::propose_action(ctx, action)
+    }
+
+    pub fn approve_action(ctx: Context<ApproveAction>, proposal_id: u64) -> Result<()> {
+        multisig::approve_action(ctx, proposal_id)
+    }
+
+    pub fn execute_action<'info>(
+        ctx: Context<'_, '_, '_, 'info, ExecuteAction<'info>>,
+        proposal_id: u64,
+    ) -> Result<()> {
+        multisig::execute_action(ctx, proposal_id)
+    }
 }
@@ -1,7 +1,16 @@
 use {
     anchor_lang::{
         prelude::*,
-        solana_program::program::invoke_signed,
+        AccountsClose,
+        solana_program::{
+            ed25519_program,
+            hash::hashv,
+            instruction::{get_stack_height, TRANSACTION_LEVEL_STACK_HEIGHT},
+            entrypoint::MAX_PERMITTED_DATA_INCREASE,
+            keccak,
+            program::invoke_signed,
+            sysvar,
+        },
         system_program,
     },
     anchor_spl::{
@@ -11,7 +20,7 @@ use {
     mpl_token_metadata::{
         ID as TOKEN_METADATA_ID,
         instruction as token_instruction,
-        state::{CollectionDetails, DataV2},
+        state::{CollectionDetails, DataV2, Metadata, TokenMetadataAccount},
         // assertions::collection::assert_master_edition,
         utils::assert_derivation,
     },
@@ -23,17 +32,56 @@ pub fn initialize(
     symbol: String,
     base_token_uri: String,
     price_lamports: u64,
+    max_supply: u64,
 ) -> Result<()> {
+    if max_supply == 0 {
+        return Err(error!(ErrorCode::InvalidMaxSupply));
+    }
+
     // set nft pda
     let nft_pda = &mut ctx.accounts.nft_pda;
 
     nft_pda.creator = ctx.accounts.nft_manager.key();
+    nft_pda.pending_creator = None;
+    nft_pda.price_admin = ctx.accounts.nft_manager.key();
+    nft_pda.metadata_admin = ctx.accounts.nft_manager.key();
+    nft_pda.treasury_admin = ctx.accounts.nft_manager.key();
+    nft_pda.collection_admin = ctx.accounts.nft_manager.key();
     nft_pda.name = name;
     nft_pda.symbol = symbol;
     nft_pda.base_token_uri = base_token_uri;
     nft_pda.price_lamports = price_lamports;
+    nft_pda.max_supply = max_supply;
+    nft_pda.minted_count = 0;
+    nft_pda.mint_mode = MintMode::Explicit;
+    nft_pda.next_token_id = 1;
+    nft_pda.payment_mint = None;
+    nft_pda.price_tokens = 0;
+    nft_pda.sale_phases = Vec::new();
+    nft_pda.next_phase_id = 0;
+    nft_pda.allowlist_root = None;
+    nft_pda.gate_collection = None;
+    nft_pda.pricing_mode = PricingMode::Fixed;
+    nft_pda.soft_cap = 0;
+    nft_pda.soft_cap_deadline = 0;
+    nft_pda.raised = 0;
+    nft_pda.voucher_signer = None;
+    nft_pda.paused = false;
+    nft_pda.referral_bps = 0;
+    nft_pda.price_overrides = Vec::new();
+    nft_pda.redemption = None;
+    nft_pda.multisig_enabled = false;
+    nft_pda.next_random_ticket = 0;
+    nft_pda.next_reveal_ticket = 0;
+    nft_pda.open_auctions = 0;
     nft_pda.bump = *ctx.bumps.get("nft_pda").unwrap();
 
+    // set mint bitmap
+    let mut mint_bitmap = ctx.accounts.mint_bitmap.load_init()?;
+
+    mint_bitmap.nft_pda = nft_pda.key();
+    mint_bitmap.capacity = max_supply;
+
     // set collection pda
     let collection_pda = &mut ctx.accounts.collection_pda;
 
@@ -43,6 +91,124 @@ pub fn initialize(
     Ok(())
 }
 
+/// Rewrites an `NftPda` created before it carried a max supply into the
+/// current layout and creates its mint bitmap. The legacy program only
+/// minted token ids 1 and 2 and kept no record of them, so the caller lists
+/// the ones already minted.
+pub fn migrate_nft_pda(
+    ctx: Context<MigrateNftPda>,
+    max_supply: u64,
+    minted_token_ids: Vec<u64>,
+) -> Result<()> {
+    let nft_pda_info = ctx.accounts.nft_pda.to_account_info();
+
+    let legacy = {
+        let data = nft_pda_info.try_borrow_data()?;
+
+        if nft_pda_info.owner != &crate::id()
+            || data.len() != LegacyNftPda::SPACE
+            || data[..8] != <NftPda as anchor_lang::Discriminator>::discriminator()
+        {
+            return Err(error!(ErrorCode::InvalidMigration));
+        }
+
+        LegacyNftPda::deserialize(&mut &data[8..])?
+    };
+
+    if &legacy.creator != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    if max_supply == 0 {
+        return Err(error!(ErrorCode::InvalidMaxSupply));
+    }
+
+    {
+        let mint_bitmap_info = ctx.accounts.mint_bitmap.to_account_info();
+        let mut data = mint_bitmap_info.try_borrow_mut_data()?;
+        let bits = &mut data[MintBitmap::BITS_OFFSET..];
+
+        for token_id in &minted_token_ids {
+            if *token_id > LegacyNftPda::MAX_TOKEN_ID || *token_id > max_supply {
+                return Err(error!(ErrorCode::InvalidTokenId));
+            }
+
+            if MintBitmap::is_minted(bits, *token_id)? {
+                return Err(error!(ErrorCode::TokenAlreadyMinted));
+            }
+
+            MintBitmap::set_minted(bits, *token_id)?;
+        }
+    }
+
+    let mut mint_bitmap = ctx.accounts.mint_bitmap.load_init()?;
+
+    mint_bitmap.nft_pda = nft_pda_info.key();
+    mint_bitmap.capacity = max_supply;
+
+    drop(mint_bitmap);
+
+    let space = NftPda::SPACE;
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(space);
+    let top_up = rent_exempt_minimum.saturating_sub(nft_pda_info.lamports());
+
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: nft_pda_info.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+
+    nft_pda_info.realloc(space, false)?;
+
+    let nft_pda = NftPda {
+        creator: legacy.creator,
+        pending_creator: None,
+        price_admin: legacy.creator,
+        metadata_admin: legacy.creator,
+        treasury_admin: legacy.creator,
+        collection_admin: legacy.creator,
+        name: legacy.name,
+        symbol: legacy.symbol,
+        base_token_uri: legacy.base_token_uri,
+        price_lamports: legacy.price_lamports,
+        max_supply,
+        minted_count: minted_token_ids.len() as u64,
+        mint_mode: MintMode::Explicit,
+        next_token_id: 1,
+        payment_mint: None,
+        price_tokens: 0,
+        sale_phases: Vec::new(),
+        next_phase_id: 0,
+        allowlist_root: None,
+        gate_collection: None,
+        pricing_mode: PricingMode::Fixed,
+        soft_cap: 0,
+        soft_cap_deadline: 0,
+        raised: 0,
+        voucher_signer: None,
+        paused: false,
+        referral_bps: 0,
+        price_overrides: Vec::new(),
+        redemption: None,
+        multisig_enabled: false,
+        next_random_ticket: 0,
+        next_reveal_ticket: 0,
+        open_auctions: 0,
+        bump: legacy.bump,
+    };
+
+    nft_pda.try_serialize(&mut &mut nft_pda_info.try_borrow_mut_data()?[..])?;
+
+    Ok(())
+}
+
 pub fn set_metadata(
     ctx: Context<SetMetadata>,
     name: String,
@@ -51,10 +217,14 @@ pub fn set_metadata(
 ) -> Result<()> {
     let nft_pda = &mut ctx.accounts.nft_pda;
 
-    if &nft_pda.creator != ctx.accounts.nft_manager.key {
+    if &nft_pda.metadata_admin != ctx.accounts.authority.key {
         return Err(error!(ErrorCode::Unauthorized));
     }
 
+    if nft_pda.multisig_enabled {
+        return Err(error!(ErrorCode::MultisigRequired));
+    }
+
     nft_pda.name = name;
     nft_pda.symbol = symbol;
     nft_pda.base_token_uri = base_token_uri;
@@ -64,239 +234,1750 @@ pub fn set_metadata(
 pub fn set_price(ctx: Context<SetPrice>, price_lamports: u64) -> Result<()> {
     let nft_pda = &mut ctx.accounts.nft_pda;
 
-    if &nft_pda.creator != ctx.accounts.nft_manager.key {
+    if &nft_pda.price_admin != ctx.accounts.authority.key {
         return Err(error!(ErrorCode::Unauthorized));
     }
 
+    if nft_pda.multisig_enabled {
+        return Err(error!(ErrorCode::MultisigRequired));
+    }
+
     nft_pda.price_lamports = price_lamports;
     Ok(())
 }
 
-pub fn mint_collection(
-    ctx: Context<MintCollection>, 
-) -> Result<()> {
+pub fn set_price_overrides(ctx: Context<SetPriceOverrides>, price_overrides: Vec<PriceOverride>) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.price_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    NftPda::validate_price_overrides(&price_overrides)?;
+
+    nft_pda.price_overrides = price_overrides;
+    Ok(())
+}
+
+pub fn withdraw(ctx: Context<Withdraw>, amount: u64) -> Result<()> {
     let nft_pda = &ctx.accounts.nft_pda;
 
-    if &nft_pda.creator != ctx.accounts.nft_manager.key {
-        return Err(error!(ErrorCode::InvalidNftManager));
+    if &nft_pda.treasury_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
     }
 
-    let collection_pda = &mut ctx.accounts.collection_pda;
+    // once payees are configured, proceeds only leave through `distribute`
+    if !ctx.accounts.revenue_split.data_is_empty() {
+        return Err(error!(ErrorCode::RevenueSplitActive));
+    }
 
-    if &collection_pda.authority != nft_pda.to_account_info().key {
-        return Err(error!(ErrorCode::InvalidCollectionAuthority));
+    let nft_pda_info = nft_pda.to_account_info();
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(nft_pda_info.data_len());
+    let available = nft_pda_info.lamports().saturating_sub(rent_exempt_minimum);
+
+    if amount > available {
+        return Err(error!(ErrorCode::InsufficientFunds));
     }
 
-    msg!("Creating mint account...");
-    system_program::create_account(
-        CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            system_program::CreateAccount {
-                from: ctx.accounts.mint_authority.to_account_info(),
-                to: ctx.accounts.mint.to_account_info(),
-            },
-        ),
-        10000000,
-        82,
-        &ctx.accounts.token_program.key(),
-    )?;
+    msg!("Withdrawing {} lamports...", amount);
+    **nft_pda_info.try_borrow_mut_lamports()? -= amount;
+    **ctx.accounts.destination.try_borrow_mut_lamports()? += amount;
 
-    msg!("Initializing mint account...");
-    token::initialize_mint(
-        CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            token::InitializeMint {
-                mint: ctx.accounts.mint.to_account_info(),
-                rent: ctx.accounts.rent.to_account_info(),
-            },
-        ),
-        0,
-        &ctx.accounts.mint_authority.key(),
-        Some(&ctx.accounts.mint_authority.key()),
-    )?;
+    Ok(())
+}
 
-    msg!("Creating token account...");
-    associated_token::create(
-        CpiContext::new(
-            ctx.accounts.associated_token_program.to_account_info(),
-            associated_token::Create {
-                payer: ctx.accounts.mint_authority.to_account_info(),
-                associated_token: ctx.accounts.token_account.to_account_info(),
-                authority: ctx.accounts.mint_authority.to_account_info(),
-                mint: ctx.accounts.mint.to_account_info(),
-                system_program: ctx.accounts.system_program.to_account_info(),
-                token_program: ctx.accounts.token_program.to_account_info(),
-                rent: ctx.accounts.rent.to_account_info(),
-            },
-        ),
-    )?;
+pub fn set_revenue_split(ctx: Context<SetRevenueSplit>, payees: Vec<Payee>) -> Result<()> {
+    let nft_pda = &ctx.accounts.nft_pda;
 
-    msg!("Minting token to token account...");
-    token::mint_to(
-        CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
-            token::MintTo {
-                mint: ctx.accounts.mint.to_account_info(),
-                to: ctx.accounts.token_account.to_account_info(),
-                authority: ctx.accounts.mint_authority.to_account_info(),
-            },
-        ),
-        1,
-    )?;
+    if &nft_pda.treasury_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
 
-    let name = nft_pda.name.to_string();
-    let symbol = nft_pda.symbol.to_string();
-    let uri = nft_pda.base_token_uri.to_string() + &std::string::ToString::to_string("collection.json");
+    if payees.is_empty() || payees.len() > RevenueSplit::MAX_PAYEES {
+        return Err(error!(ErrorCode::InvalidRevenueSplit));
+    }
 
-    let nft_manager = ctx.accounts.nft_manager.to_account_info();
-    let nft_manager_key = nft_manager.key();
+    let total_bps: u32 = payees.iter().map(|payee| payee.share_bps as u32).sum();
+    if total_bps != 10_000 {
+        return Err(error!(ErrorCode::InvalidRevenueSplit));
+    }
 
-    let creators = vec![
-        mpl_token_metadata::state::Creator {
-            address: nft_manager_key,
-            verified: false,
-            share: 100,
-        },
-    ];
+    let revenue_split = &mut ctx.accounts.revenue_split;
 
-    let seeds = [b"nft_pda".as_ref(), nft_manager_key.as_ref()];
-    let bump = assert_derivation(&crate::id(), &nft_pda.to_account_info(), &seeds)?;
-    let signer_seeds = [b"nft_pda".as_ref(), nft_manager_key.as_ref(), &[bump]];
+    revenue_split.payees = payees;
+    revenue_split.bump = *ctx.bumps.get("revenue_split").unwrap();
 
-    msg!("Creating metadata account...");
-    invoke_signed(
-        &token_instruction::create_metadata_accounts_v3(
-            TOKEN_METADATA_ID, 
-            ctx.accounts.metadata.key(), // metadata_account
-            ctx.accounts.mint.key(),  // mint_account
-            ctx.accounts.mint_authority.key(), // Mint authority
-            ctx.accounts.mint_authority.key(), // Payer
-            nft_pda.key(), // Update authority
-            name, 
-            symbol, 
-            uri, 
-            Some(creators),
-            200, // seller_fee_basis_points
-            false, // update_authority_is_signer, 
-            true, // is_mutable, 
-            None, // Option<Collection>
-            None, // Option<Uses>
-            Some(CollectionDetails::V1 { size: 0 }), // Option<CollectionDetails>
-        ),
-        &[
-            ctx.accounts.metadata.to_account_info(),
-            ctx.accounts.mint.to_account_info(),
-            ctx.accounts.mint_authority.to_account_info(),
-            ctx.accounts.mint_authority.to_account_info(),
-            nft_pda.to_account_info(),
-            ctx.accounts.rent.to_account_info(),
-        ],
-        &[&signer_seeds],
-    )?;
+    Ok(())
+}
 
-    msg!("Creating master edition metadata account...");
-    invoke_signed(
-        &token_instruction::create_master_edition_v3(
-            TOKEN_METADATA_ID, 
-            ctx.accounts.master_edition.key(), // // (master) edition account
-            ctx.accounts.mint.key(), // mint account
-            nft_pda.key(), // Update authority
-            ctx.accounts.mint_authority.key(), // Mint authority
-            ctx.accounts.metadata.key(), // Metadata
-            ctx.accounts.mint_authority.key(), // Payer
-            Some(0), // max_supply: Option<u64>
-        ),
-        &[
-            ctx.accounts.master_edition.to_account_info(),
-            ctx.accounts.mint.to_account_info(),
-            nft_pda.to_account_info(),
-            ctx.accounts.mint_authority.to_account_info(),
-            ctx.accounts.metadata.to_account_info(),
-            ctx.accounts.mint_authority.to_account_info(),
-            ctx.accounts.rent.to_account_info(),
-        ],
-        &[&signer_seeds],
-    )?;
+pub fn distribute<'info>(ctx: Context<'_, '_, '_, 'info, Distribute<'info>>) -> Result<()> {
+    let payees = &ctx.accounts.revenue_split.payees;
 
-    collection_pda.mint = ctx.accounts.mint.key();
+    if ctx.remaining_accounts.len() != payees.len() {
+        return Err(error!(ErrorCode::InvalidPayee));
+    }
 
-    msg!("Token mint process completed successfully.");
+    let nft_pda_info = ctx.accounts.nft_pda.to_account_info();
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(nft_pda_info.data_len());
+    let available = nft_pda_info.lamports().saturating_sub(rent_exempt_minimum);
+
+    msg!("Distributing {} lamports...", available);
+    for (payee, payee_info) in payees.iter().zip(ctx.remaining_accounts.iter()) {
+        if &payee.address != payee_info.key {
+            return Err(error!(ErrorCode::InvalidPayee));
+        }
+
+        let share = (available as u128 * payee.share_bps as u128 / 10_000) as u64;
+
+        **nft_pda_info.try_borrow_mut_lamports()? -= share;
+        **payee_info.try_borrow_mut_lamports()? += share;
+    }
 
     Ok(())
 }
 
-pub fn mint(
-    ctx: Context<MintNft>,
-    token_id: u64,
-) -> Result<()> {
-    if !(token_id == 1 || token_id == 2) {
-        return Err(error!(ErrorCode::InvalidTokenId));
+pub fn set_payment_mint(ctx: Context<SetPaymentMint>, price_tokens: u64) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.price_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
     }
 
-    let nft_pda = &ctx.accounts.nft_pda;
+    // the token price is set through `AdminAction::SetPriceTokens` instead
+    if nft_pda.multisig_enabled && price_tokens != nft_pda.price_tokens {
+        return Err(error!(ErrorCode::MultisigRequired));
+    }
 
-    if &nft_pda.creator != ctx.accounts.nft_manager.key {
-        return Err(error!(ErrorCode::InvalidNftManager));
+    if nft_pda.soft_cap > 0 {
+        return Err(error!(ErrorCode::SoftCapActive));
     }
 
-    let collection_pda = &ctx.accounts.collection_pda;
+    nft_pda.payment_mint = Some(ctx.accounts.payment_mint.key());
+    nft_pda.price_tokens = price_tokens;
+    Ok(())
+}
 
-    if &collection_pda.mint != ctx.accounts.collection_mint.key {
-        return Err(error!(ErrorCode::InvalidCollectionMint)); 
+pub fn clear_payment_mint(ctx: Context<ClearPaymentMint>) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.price_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
     }
 
-    msg!("Initiating transfer of {} lamports...", nft_pda.price_lamports);
-    system_program::transfer(
-        CpiContext::new(
-            ctx.accounts.system_program.to_account_info(),
-            system_program::Transfer {
-                from: ctx.accounts.payer.to_account_info(),
-                to: nft_pda.to_account_info(),
-            }
-        ),
-        nft_pda.price_lamports
-    )?;
+    nft_pda.payment_mint = None;
+    Ok(())
+}
 
-    msg!("Creating mint account...");
-    system_program::create_account(
-        CpiContext::new(
+pub fn set_redemption(ctx: Context<SetRedemption>, redemption: Option<Redemption>) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.price_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    // redemptions take no payment to escrow
+    if nft_pda.soft_cap > 0 {
+        return Err(error!(ErrorCode::SoftCapActive));
+    }
+
+    nft_pda.redemption = redemption;
+    Ok(())
+}
+
+pub fn withdraw_tokens(ctx: Context<WithdrawTokens>, amount: u64) -> Result<()> {
+    let nft_pda = &ctx.accounts.nft_pda;
+
+    if &nft_pda.treasury_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    // once payees are configured, proceeds only leave through `distribute_tokens`
+    if !ctx.accounts.revenue_split.data_is_empty() {
+        return Err(error!(ErrorCode::RevenueSplitActive));
+    }
+
+    let nft_manager_key = ctx.accounts.nft_manager.key();
+
+    let seeds = [b"nft_pda".as_ref(), nft_manager_key.as_ref()];
+    let bump = assert_derivation(&crate::id(), &nft_pda.to_account_info(), &seeds)?;
+    let signer_seeds = [b"nft_pda".as_ref(), nft_manager_key.as_ref(), &[bump]];
+
+    msg!("Withdrawing {} tokens...", amount);
+    token::transfer(
+        CpiContext::new_with_signer(
             ctx.accounts.token_program.to_account_info(),
-            system_program::CreateAccount {
-                from: ctx.accounts.payer.to_account_info(),
-                to: ctx.accounts.mint.to_account_info(),
+            token::Transfer {
+                from: ctx.accounts.treasury.to_account_info(),
+                to: ctx.accounts.destination.to_account_info(),
+                authority: nft_pda.to_account_info(),
             },
+            &[&signer_seeds],
         ),
-        10000000, // Lamports
-        82, // Size
-        &ctx.accounts.token_program.key(), // Token Program owns the Mint account
+        amount,
     )?;
 
-    msg!("Initializing mint account...");
-    token::initialize_mint(
-        CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
+    Ok(())
+}
+
+/// Splits the token treasury between the revenue split's payees. Takes one
+/// token account of the payment mint per payee, owned by that payee and in
+/// the same order, as remaining accounts.
+pub fn distribute_tokens<'info>(ctx: Context<'_, '_, '_, 'info, DistributeTokens<'info>>) -> Result<()> {
+    let payees = &ctx.accounts.revenue_split.payees;
+
+    if ctx.remaining_accounts.len() != payees.len() {
+        return Err(error!(ErrorCode::InvalidPayee));
+    }
+
+    let nft_pda = &ctx.accounts.nft_pda;
+    let nft_manager_key = ctx.accounts.nft_manager.key();
+
+    let seeds = [b"nft_pda".as_ref(), nft_manager_key.as_ref()];
+    let bump = assert_derivation(&crate::id(), &nft_pda.to_account_info(), &seeds)?;
+    let signer_seeds = [b"nft_pda".as_ref(), nft_manager_key.as_ref(), &[bump]];
+
+    let available = ctx.accounts.treasury.amount;
+
+    msg!("Distributing {} tokens...", available);
+    for (payee, payee_info) in payees.iter().zip(ctx.remaining_accounts.iter()) {
+        let payee_token_account = Account::<token::TokenAccount>::try_from(payee_info)?;
+
+        if payee_token_account.owner != payee.address || payee_token_account.mint != ctx.accounts.payment_mint.key() {
+            return Err(error!(ErrorCode::InvalidPayee));
+        }
+
+        let share = (available as u128 * payee.share_bps as u128 / 10_000) as u64;
+
+        token::transfer(
+            CpiContext::new_with_signer(
+                ctx.accounts.token_program.to_account_info(),
+                token::Transfer {
+                    from: ctx.accounts.treasury.to_account_info(),
+                    to: payee_info.clone(),
+                    authority: nft_pda.to_account_info(),
+                },
+                &[&signer_seeds],
+            ),
+            share,
+        )?;
+    }
+
+    Ok(())
+}
+
+pub fn set_pricing_mode(ctx: Context<SetPricingMode>, pricing_mode: PricingMode) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.price_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    pricing_mode.validate()?;
+
+    nft_pda.pricing_mode = pricing_mode;
+    Ok(())
+}
+
+pub fn set_soft_cap(ctx: Context<SetSoftCap>, soft_cap: u64, deadline: i64) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.treasury_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    if nft_pda.minted_count > 0 {
+        return Err(error!(ErrorCode::MintAlreadyStarted));
+    }
+
+    // escrow and refunds are in lamports only
+    if nft_pda.payment_mint.is_some() {
+        return Err(error!(ErrorCode::SoftCapActive));
+    }
+
+    if nft_pda.redemption.is_some() {
+        return Err(error!(ErrorCode::InvalidSoftCap));
+    }
+
+    if soft_cap == 0 || deadline <= Clock::get()?.unix_timestamp {
+        return Err(error!(ErrorCode::InvalidSoftCap));
+    }
+
+    nft_pda.soft_cap = soft_cap;
+    nft_pda.soft_cap_deadline = deadline;
+    ctx.accounts.vault.bump = *ctx.bumps.get("vault").unwrap();
+
+    Ok(())
+}
+
+/// Moves escrowed lamports into `nft_pda`, where `withdraw` or, with a
+/// revenue split, `distribute` pays them out.
+pub fn release_escrow(ctx: Context<ReleaseEscrow>, amount: u64) -> Result<()> {
+    let nft_pda = &ctx.accounts.nft_pda;
+
+    if &nft_pda.treasury_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    if nft_pda.raised < nft_pda.soft_cap {
+        return Err(error!(ErrorCode::SoftCapNotReached));
+    }
+
+    let vault_info = ctx.accounts.vault.to_account_info();
+    let rent_exempt_minimum = Rent::get()?.minimum_balance(vault_info.data_len());
+    let available = vault_info.lamports().saturating_sub(rent_exempt_minimum);
+
+    if amount > available {
+        return Err(error!(ErrorCode::InsufficientFunds));
+    }
+
+    msg!("Releasing {} lamports from escrow...", amount);
+    **vault_info.try_borrow_mut_lamports()? -= amount;
+    **nft_pda.to_account_info().try_borrow_mut_lamports()? += amount;
+
+    Ok(())
+}
+
+pub fn refund(ctx: Context<Refund>, _token_id: u64) -> Result<()> {
+    let nft_pda = &ctx.accounts.nft_pda;
+
+    if nft_pda.soft_cap == 0 || !nft_pda.soft_cap_failed(Clock::get()?.unix_timestamp) {
+        return Err(error!(ErrorCode::RefundUnavailable));
+    }
+
+    let wallet_mints = &ctx.accounts.wallet_mints;
+
+    if wallet_mints.escrowed_count == 0 {
+        return Err(error!(ErrorCode::RefundUnavailable));
+    }
+
+    // only tokens the buyer minted from this sale carry an escrowed payment
+    let token_record = &ctx.accounts.token_record;
+
+    if token_record.mint != ctx.accounts.mint.key() || &token_record.owner != ctx.accounts.buyer.key {
+        return Err(error!(ErrorCode::InvalidTokenRecord));
+    }
+
+    let metadata = Metadata::from_account_info(&ctx.accounts.metadata)?;
+
+    if metadata.mint != ctx.accounts.mint.key() {
+        return Err(error!(ErrorCode::NotCollectionMember));
+    }
+
+    match metadata.collection {
+        Some(collection) if collection.verified && collection.key == ctx.accounts.collection_pda.mint => {}
+        _ => return Err(error!(ErrorCode::NotCollectionMember)),
+    }
+
+    msg!("Burning token...");
+    token::burn(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::Burn {
+                mint: ctx.accounts.mint.to_account_info(),
+                from: ctx.accounts.token_account.to_account_info(),
+                authority: ctx.accounts.buyer.to_account_info(),
+            },
+        ),
+        1,
+    )?;
+
+    // refund an equal share of what the buyer escrowed per token
+    let amount = wallet_mints.escrowed_lamports / wallet_mints.escrowed_count as u64;
+
+    msg!("Refunding {} lamports...", amount);
+    **ctx.accounts.vault.to_account_info().try_borrow_mut_lamports()? -= amount;
+    **ctx.accounts.buyer.to_account_info().try_borrow_mut_lamports()? += amount;
+
+    let wallet_mints = &mut ctx.accounts.wallet_mints;
+
+    wallet_mints.escrowed_lamports -= amount;
+    wallet_mints.escrowed_count -= 1;
+
+    ctx.accounts.nft_pda.raised -= amount;
+
+    Ok(())
+}
+
+pub fn pause(ctx: Context<Pause>) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.creator != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    nft_pda.paused = true;
+    Ok(())
+}
+
+pub fn unpause(ctx: Context<Unpause>) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.creator != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    nft_pda.paused = false;
+    Ok(())
+}
+
+pub fn set_role(ctx: Context<SetRole>, role: Role, admin: Pubkey) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.creator != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    match role {
+        Role::Price => nft_pda.price_admin = admin,
+        Role::Metadata => nft_pda.metadata_admin = admin,
+        Role::Treasury => nft_pda.treasury_admin = admin,
+        Role::Collection => nft_pda.collection_admin = admin,
+    }
+    Ok(())
+}
+
+pub fn propose_manager(ctx: Context<ProposeManager>, new_manager: Pubkey) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.creator != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    nft_pda.pending_creator = Some(new_manager);
+    Ok(())
+}
+
+pub fn cancel_manager_proposal(ctx: Context<CancelManagerProposal>) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.creator != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    nft_pda.pending_creator = None;
+    Ok(())
+}
+
+pub fn accept_manager(ctx: Context<AcceptManager>) -> Result<()> {
+    let nft_pda: &mut NftPda = &mut ctx.accounts.nft_pda;
+
+    if nft_pda.pending_creator != Some(ctx.accounts.authority.key()) {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    let previous_creator = nft_pda.creator;
+    let new_creator = ctx.accounts.authority.key();
+
+    // roles still held by the outgoing key move with it, so a handover away
+    // from a lost or compromised key leaves it nothing
+    for role in [
+        &mut nft_pda.price_admin,
+        &mut nft_pda.metadata_admin,
+        &mut nft_pda.treasury_admin,
+        &mut nft_pda.collection_admin,
+    ] {
+        if *role == previous_creator {
+            *role = new_creator;
+        }
+    }
+
+    nft_pda.creator = new_creator;
+    nft_pda.pending_creator = None;
+    Ok(())
+}
+
+pub fn set_max_supply(ctx: Context<SetMaxSupply>, max_supply: u64) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.creator != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    // the random id pool is sized once, so its supply is fixed
+    if nft_pda.mint_mode == MintMode::Random {
+        return Err(error!(ErrorCode::InvalidMintMode));
+    }
+
+    // supply can only shrink, and never below what has already been minted
+    if max_supply > nft_pda.max_supply || max_supply < nft_pda.minted_count {
+        return Err(error!(ErrorCode::InvalidMaxSupply));
+    }
+
+    // shrinking could strand a token id reserved by an auction
+    if nft_pda.open_auctions > 0 {
+        return Err(error!(ErrorCode::AuctionOpen));
+    }
+
+    // explicit mints can land anywhere in the range, not just below minted_count
+    {
+        let mint_bitmap = ctx.accounts.mint_bitmap.to_account_info();
+        let data = mint_bitmap.try_borrow_data()?;
+
+        if MintBitmap::any_minted_above(&data[MintBitmap::BITS_OFFSET..], max_supply) {
+            return Err(error!(ErrorCode::InvalidMaxSupply));
+        }
+    }
+
+    nft_pda.max_supply = max_supply;
+    Ok(())
+}
+
+pub fn set_mint_mode(ctx: Context<SetMintMode>, mint_mode: MintMode) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.creator != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    // switching modes mid-sale could hand out ids that were already minted
+    if nft_pda.minted_count > 0 {
+        return Err(error!(ErrorCode::MintAlreadyStarted));
+    }
+
+    // auctions reserve ids the other modes don't know about
+    if nft_pda.open_auctions > 0 {
+        return Err(error!(ErrorCode::AuctionOpen));
+    }
+
+    nft_pda.mint_mode = mint_mode;
+    Ok(())
+}
+
+pub fn set_allowlist_root(ctx: Context<SetAllowlistRoot>, allowlist_root: Option<[u8; 32]>) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.creator != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    nft_pda.allowlist_root = allowlist_root;
+    Ok(())
+}
+
+pub fn set_gate_collection(ctx: Context<SetGateCollection>, gate_collection: Option<Pubkey>) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.creator != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    nft_pda.gate_collection = gate_collection;
+    Ok(())
+}
+
+pub fn set_voucher_signer(ctx: Context<SetVoucherSigner>, voucher_signer: Option<Pubkey>) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.price_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    nft_pda.voucher_signer = voucher_signer;
+    Ok(())
+}
+
+pub fn set_referral_bps(ctx: Context<SetReferralBps>, referral_bps: u16) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.treasury_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    if referral_bps > 10_000 {
+        return Err(error!(ErrorCode::InvalidReferralShare));
+    }
+
+    nft_pda.referral_bps = referral_bps;
+    Ok(())
+}
+
+pub fn register_referrer(ctx: Context<RegisterReferrer>) -> Result<()> {
+    let referral_record = &mut ctx.accounts.referral_record;
+
+    referral_record.referrer = ctx.accounts.referrer.key();
+    referral_record.referred_count = 0;
+    referral_record.total_lamports = 0;
+    referral_record.bump = *ctx.bumps.get("referral_record").unwrap();
+
+    Ok(())
+}
+
+pub fn add_sale_phase(
+    ctx: Context<AddSalePhase>,
+    start_ts: i64,
+    end_ts: i64,
+    price: u64,
+    per_wallet_cap: u16,
+) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.price_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    if nft_pda.sale_phases.len() >= NftPda::MAX_SALE_PHASES || nft_pda.next_phase_id as usize >= WalletMints::MAX_PHASE_IDS {
+        return Err(error!(ErrorCode::InvalidSalePhase));
+    }
+
+    let phase = SalePhase {
+        id: nft_pda.next_phase_id,
+        start_ts,
+        end_ts,
+        price,
+        per_wallet_cap,
+    };
+    nft_pda.validate_sale_phase(&phase, Clock::get()?.unix_timestamp)?;
+
+    nft_pda.sale_phases.push(phase);
+    nft_pda.next_phase_id += 1;
+    Ok(())
+}
+
+pub fn edit_sale_phase(
+    ctx: Context<EditSalePhase>,
+    phase_id: u8,
+    start_ts: i64,
+    end_ts: i64,
+    price: u64,
+    per_wallet_cap: u16,
+) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.price_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    let now = Clock::get()?.unix_timestamp;
+    let index = nft_pda.pending_sale_phase_index(phase_id, now)?;
+
+    let phase = SalePhase {
+        id: phase_id,
+        start_ts,
+        end_ts,
+        price,
+        per_wallet_cap,
+    };
+    nft_pda.validate_sale_phase(&phase, now)?;
+
+    nft_pda.sale_phases[index] = phase;
+    Ok(())
+}
+
+pub fn remove_sale_phase(ctx: Context<RemoveSalePhase>, phase_id: u8) -> Result<()> {
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    if &nft_pda.price_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    let index = nft_pda.pending_sale_phase_index(phase_id, Clock::get()?.unix_timestamp)?;
+
+    nft_pda.sale_phases.remove(index);
+    Ok(())
+}
+
+pub fn free_token_ids(
+    ctx: Context<FreeTokenIds>,
+    start: u64,
+    limit: u8,
+) -> Result<Vec<u64>> {
+    let nft_pda = &ctx.accounts.nft_pda;
+    let mint_bitmap = ctx.accounts.mint_bitmap.to_account_info();
+    let data = mint_bitmap.try_borrow_data()?;
+
+    Ok(MintBitmap::free_token_ids(
+        &data[MintBitmap::BITS_OFFSET..],
+        nft_pda.max_supply,
+        start,
+        limit.min(MintBitmap::MAX_FREE_TOKEN_IDS) as usize,
+    ))
+}
+
+pub fn mint_collection(
+    ctx: Context<MintCollection>, 
+) -> Result<()> {
+    let nft_pda = &ctx.accounts.nft_pda;
+
+    if &nft_pda.collection_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::InvalidNftManager));
+    }
+
+    let collection_pda = &mut ctx.accounts.collection_pda;
+
+    if &collection_pda.authority != nft_pda.to_account_info().key {
+        return Err(error!(ErrorCode::InvalidCollectionAuthority));
+    }
+
+    msg!("Creating mint account...");
+    system_program::create_account(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            system_program::CreateAccount {
+                from: ctx.accounts.mint_authority.to_account_info(),
+                to: ctx.accounts.mint.to_account_info(),
+            },
+        ),
+        10000000,
+        82,
+        &ctx.accounts.token_program.key(),
+    )?;
+
+    msg!("Initializing mint account...");
+    token::initialize_mint(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::InitializeMint {
+                mint: ctx.accounts.mint.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+            },
+        ),
+        0,
+        &ctx.accounts.mint_authority.key(),
+        Some(&ctx.accounts.mint_authority.key()),
+    )?;
+
+    msg!("Creating token account...");
+    associated_token::create(
+        CpiContext::new(
+            ctx.accounts.associated_token_program.to_account_info(),
+            associated_token::Create {
+                payer: ctx.accounts.mint_authority.to_account_info(),
+                associated_token: ctx.accounts.token_account.to_account_info(),
+                authority: ctx.accounts.mint_authority.to_account_info(),
+                mint: ctx.accounts.mint.to_account_info(),
+                system_program: ctx.accounts.system_program.to_account_info(),
+                token_program: ctx.accounts.token_program.to_account_info(),
+                rent: ctx.accounts.rent.to_account_info(),
+            },
+        ),
+    )?;
+
+    msg!("Minting token to token account...");
+    token::mint_to(
+        CpiContext::new(
+            ctx.accounts.token_program.to_account_info(),
+            token::MintTo {
+                mint: ctx.accounts.mint.to_account_info(),
+                to: ctx.accounts.token_account.to_account_info(),
+                authority: ctx.accounts.mint_authority.to_account_info(),
+            },
+        ),
+        1,
+    )?;
+
+    let name = nft_pda.name.to_string();
+    let symbol = nft_pda.symbol.to_string();
+    let uri = nft_pda.base_token_uri.to_string() + &std::string::ToString::to_string("collection.json");
+
+    let nft_manager = ctx.accounts.nft_manager.to_account_info();
+    let nft_manager_key = nft_manager.key();
+
+    let creators = vec![
+        mpl_token_metadata::state::Creator {
+            address: nft_pda.creator,
+            verified: false,
+            share: 100,
+        },
+    ];
+
+    let seeds = [b"nft_pda".as_ref(), nft_manager_key.as_ref()];
+    let bump = assert_derivation(&crate::id(), &nft_pda.to_account_info(), &seeds)?;
+    let signer_seeds = [b"nft_pda".as_ref(), nft_manager_key.as_ref(), &[bump]];
+
+    msg!("Creating metadata account...");
+    invoke_signed(
+        &token_instruction::create_metadata_accounts_v3(
+            TOKEN_METADATA_ID, 
+            ctx.accounts.metadata.key(), // metadata_account
+            ctx.accounts.mint.key(),  // mint_account
+            ctx.accounts.mint_authority.key(), // Mint authority
+            ctx.accounts.mint_authority.key(), // Payer
+            nft_pda.key(), // Update authority
+            name, 
+            symbol, 
+            uri, 
+            Some(creators),
+            200, // seller_fee_basis_points
+            false, // update_authority_is_signer, 
+            true, // is_mutable, 
+            None, // Option<Collection>
+            None, // Option<Uses>
+            Some(CollectionDetails::V1 { size: 0 }), // Option<CollectionDetails>
+        ),
+        &[
+            ctx.accounts.metadata.to_account_info(),
+            ctx.accounts.mint.to_account_info(),
+            ctx.accounts.mint_authority.to_account_info(),
+            ctx.accounts.mint_authority.to_account_info(),
+            nft_pda.to_account_info(),
+            ctx.accounts.rent.to_account_info(),
+        ],
+        &[&signer_seeds],
+    )?;
+
+    msg!("Creating master edition metadata account...");
+    invoke_signed(
+        &token_instruction::create_master_edition_v3(
+            TOKEN_METADATA_ID, 
+            ctx.accounts.master_edition.key(), // // (master) edition account
+            ctx.accounts.mint.key(), // mint account
+            nft_pda.key(), // Update authority
+            ctx.accounts.mint_authority.key(), // Mint authority
+            ctx.accounts.metadata.key(), // Metadata
+            ctx.accounts.mint_authority.key(), // Payer
+            Some(0), // max_supply: Option<u64>
+        ),
+        &[
+            ctx.accounts.master_edition.to_account_info(),
+            ctx.accounts.mint.to_account_info(),
+            nft_pda.to_account_info(),
+            ctx.accounts.mint_authority.to_account_info(),
+            ctx.accounts.metadata.to_account_info(),
+            ctx.accounts.mint_authority.to_account_info(),
+            ctx.accounts.rent.to_account_info(),
+        ],
+        &[&signer_seeds],
+    )?;
+
+    collection_pda.mint = ctx.accounts.mint.key();
+
+    msg!("Token mint process completed successfully.");
+
+    Ok(())
+}
+
+/// Remaining accounts, in order:
+/// - when a redemption is set: the payer's token account to burn from, its
+///   mint, then for a collection redemption the token's metadata account
+/// - otherwise, when a payment mint is set: the payer's token account, then
+///   the treasury
+/// - otherwise, when a soft cap is set: the escrow vault
+/// - when a gate collection is set: the payer's token account holding the
+///   gating NFT, then that NFT's metadata account
+/// - when a voucher is given: its nonce account
+/// - when a referrer is given: the referrer, then its referral record
+pub fn mint<'info>(
+    ctx: Context<'_, '_, '_, 'info, MintNft<'info>>,
+    token_id: u64,
+    proof: Vec<[u8; 32]>,
+    allocation: u16,
+    voucher: Option<MintVoucher>,
+    max_price: u64,
+) -> Result<u64> {
+    let nft_pda = &ctx.accounts.nft_pda;
+    let token_id = nft_pda.resolve_token_id(token_id);
+
+    if nft_pda.paused {
+        return Err(error!(ErrorCode::SalePaused));
+    }
+
+    if nft_pda.minted_count >= nft_pda.max_supply {
+        return Err(error!(ErrorCode::SoldOut));
+    }
+
+    if token_id == 0 || token_id > nft_pda.max_supply {
+        return Err(error!(ErrorCode::InvalidTokenId));
+    }
+
+    if nft_pda.mint_mode == MintMode::Random {
+        return Err(error!(ErrorCode::InvalidMintMode));
+    }
+
+    if ctx.accounts.token_record.mint != Pubkey::default() {
+        return Err(error!(ErrorCode::TokenAlreadyMinted));
+    }
+
+    {
+        let mint_bitmap = ctx.accounts.mint_bitmap.to_account_info();
+        let data = mint_bitmap.try_borrow_data()?;
+
+        if MintBitmap::is_minted(&data[MintBitmap::BITS_OFFSET..], token_id)? {
+            return Err(error!(ErrorCode::TokenAlreadyMinted));
+        }
+    }
+
+    let collection_pda = &ctx.accounts.collection_pda;
+
+    if &collection_pda.mint != ctx.accounts.collection_mint.key {
+        return Err(error!(ErrorCode::InvalidCollectionMint)); 
+    }
+
+    // a voucher stands in for the allowlist and overrides the price
+    match &voucher {
+        Some(voucher) => verify_voucher(nft_pda, ctx.accounts.payer.key, voucher, &ctx.accounts.instructions)?,
+        None => apply_allowlist(nft_pda, ctx.accounts.payer.key, &proof, allocation, &mut ctx.accounts.wallet_mints)?,
+    }
+    let price = apply_sale_phase(nft_pda, &mut ctx.accounts.wallet_mints)?;
+    let price = nft_pda.token_price(token_id, price);
+    let price = voucher.as_ref().map_or(price, |voucher| voucher.price);
+    // a redemption burns a token instead of taking payment
+    let price = if nft_pda.redemption.is_some() { 0 } else { price };
+
+    // guard against a price change landing before the buyer's transaction
+    if price > max_price {
+        return Err(error!(ErrorCode::PriceExceeded));
+    }
+
+    apply_soft_cap(nft_pda, price, &mut ctx.accounts.wallet_mints)?;
+
+    let remaining_accounts = &mut ctx.remaining_accounts.iter();
+    let payment_accounts = ctx.accounts.payment_accounts();
+
+    match &nft_pda.redemption {
+        Some(redemption) => redeem_token(&payment_accounts, redemption, remaining_accounts)?,
+        None => collect_payment(&payment_accounts, price, remaining_accounts)?,
+    }
+    verify_token_gate(nft_pda, ctx.accounts.payer.key, remaining_accounts)?;
+    if let Some(voucher) = &voucher {
+        consume_voucher_nonce(&payment_accounts, voucher.nonce, remaining_accounts)?;
+    }
+    pay_referrer(&payment_accounts, price, remaining_accounts)?;
+    mint_token(&ctx.accounts.mint_token_accounts(), token_id)?;
+
+    {
+        let mint_bitmap = ctx.accounts.mint_bitmap.to_account_info();
+        let mut data = mint_bitmap.try_borrow_mut_data()?;
+
+        MintBitmap::set_minted(&mut data[MintBitmap::BITS_OFFSET..], token_id)?;
+    }
+
+    let nft_pda = &mut ctx.accounts.nft_pda;
+
+    nft_pda.record_mint(price);
+    if nft_pda.mint_mode == MintMode::Sequential {
+        nft_pda.next_token_id += 1;
+    }
+
+    let token_record = &mut ctx.accounts.token_record;
+
+    token_record.token_id = token_id;
+    token_record.mint = ctx.accounts.mint.key();
+    token_record.owner = ctx.accounts.payer.key();
+    token_record.slot = Clock::get()?.slot;
+    token_record.bump = *ctx.bumps.get("token_record").unwrap();
+
+    msg!("Token mint process completed successfully.");
+
+    Ok(token_id)
+}
+
+pub fn initialize_remaining_token_ids(ctx: Context<InitializeRemainingTokenIds>) -> Result<()> {
+    let nft_pda = &ctx.accounts.nft_pda;
+
+    if &nft_pda.creator != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    if nft_pda.minted_count > 0 {
+        return Err(error!(ErrorCode::MintAlreadyStarted));
+    }
+
+    // every id in the pool must have a bit in the mint bitmap
+    if nft_pda.max_supply > ctx.accounts.mint_bitmap.load()?.capacity {
+        return Err(error!(ErrorCode::InvalidMaxSupply));
+    }
+
+    let mut remaining_token_ids = ctx.accounts.remaining_token_ids.load_init()?;
+
+    remaining_token_ids.nft_pda = nft_pda.key();
+    remaining_token_ids.remaining = nft_pda.max_supply;
+
+    Ok(())
+}
+
+/// Grows the random id pool toward one slot per token id. An account can only
+/// grow by `MAX_PERMITTED_DATA_INCREASE` per instruction, so large
+/// collections call this several times before `mint_random` opens.
+pub fn grow_remaining_token_ids(ctx: Context<GrowRemainingTokenIds>) -> Result<()> {
+    let nft_pda = &ctx.accounts.nft_pda;
+
+    if &nft_pda.creator != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    let remaining_token_ids = ctx.accounts.remaining_token_ids.to_account_info();
+    let space = RemainingTokenIds::space(nft_pda.max_supply)
+        .min(remaining_token_ids.data_len() + MAX_PERMITTED_DATA_INCREASE);
+
+    if space <= remaining_token_ids.data_len() {
+        return Err(error!(ErrorCode::InvalidRemainingTokenIds));
+    }
+
+    let top_up = Rent::get()?.minimum_balance(space).saturating_sub(remaining_token_ids.lamports());
+
+    if top_up > 0 {
+        system_program::transfer(
+            CpiContext::new(
+                ctx.accounts.system_program.to_account_info(),
+                system_program::Transfer {
+                    from: ctx.accounts.authority.to_account_info(),
+                    to: remaining_token_ids.clone(),
+                },
+            ),
+            top_up,
+        )?;
+    }
+
+    remaining_token_ids.realloc(space, true)?;
+
+    Ok(())
+}
+
+/// Pays for a random mint and queues it as a ticket; `reveal_random_mint`
+/// then draws the token id from the hash of the slot this lands in, which
+/// nobody knows when the ticket is bought. Price overrides don't apply since
+/// the token id isn't known yet.
+///
+/// Takes the same remaining accounts as `mint`.
+pub fn mint_random<'info>(
+    ctx: Context<'_, '_, '_, 'info, MintRandomNft<'info>>,
+    proof: Vec<[u8; 32]>,
+    allocation: u16,
+    voucher: Option<MintVoucher>,
+    max_price: u64,
+) -> Result<()> {
+    check_not_cpi()?;
+
+    let nft_pda = &ctx.accounts.nft_pda;
+
+    if nft_pda.paused {
+        return Err(error!(ErrorCode::SalePaused));
+    }
+
+    if nft_pda.mint_mode != MintMode::Random {
+        return Err(error!(ErrorCode::InvalidMintMode));
+    }
+
+    // queued tickets count as minted, so this also covers unrevealed ones
+    if nft_pda.minted_count >= nft_pda.max_supply {
+        return Err(error!(ErrorCode::SoldOut));
+    }
+
+    // don't sell tickets that could never be revealed
+    RemainingTokenIds::check(&ctx.accounts.remaining_token_ids, nft_pda)?;
+
+    // a voucher stands in for the allowlist and overrides the price
+    match &voucher {
+        Some(voucher) => verify_voucher(nft_pda, ctx.accounts.payer.key, voucher, &ctx.accounts.instructions)?,
+        None => apply_allowlist(nft_pda, ctx.accounts.payer.key, &proof, allocation, &mut ctx.accounts.wallet_mints)?,
+    }
+    let price = apply_sale_phase(nft_pda, &mut ctx.accounts.wallet_mints)?;
+    let price = voucher.as_ref().map_or(price, |voucher| voucher.price);
+    // a redemption burns a token instead of taking payment
+    let price = if nft_pda.redemption.is_some() { 0 } else { price };
+
+    // guard against a price change landing before the buyer's transaction
+    if price > max_price {
+        return Err(error!(ErrorCode::PriceExceeded));
+    }
+
+    apply_soft_cap(nft_pda, price, &mut ctx.accounts.wallet_mints)?;
+
+    let remaining_accounts = &mut ctx.remaining_accounts.iter();
+    let payment_accounts = ctx.accounts.payment_accounts();
+
+    match &nft_pda.redemption {
+        Some(redemption) => redeem_token(&payment_accounts, redemption, remaining_accounts)?,
+        None => collect_payment(&payment_accounts, price, remaining_accounts)?,
+    }
+    verify_token_gate(nft_pda, ctx.accounts.payer.key, remaining_accounts)?;
+    if let Some(voucher) = &voucher {
+        consume_voucher_nonce(&payment_accounts, voucher.nonce, remaining_accounts)?;
+    }
+    pay_referrer(&payment_accounts, price, remaining_accounts)?;
+
+    let nft_pda = &mut ctx.accounts.nft_pda;
+    let random_mint = &mut ctx.accounts.random_mint;
+
+    random_mint.ticket = nft_pda.next_random_ticket;
+    random_mint.owner = ctx.accounts.payer.key();
+    random_mint.commit_slot = Clock::get()?.slot;
+    random_mint.bump = *ctx.bumps.get("random_mint").unwrap();
+
+    nft_pda.record_mint(price);
+    nft_pda.next_random_ticket += 1;
+
+    msg!("Queued random mint ticket {}.", random_mint.ticket);
+
+    Ok(())
+}
+
+/// Draws the token id for a queued random mint ticket and mints it to the
+/// ticket's owner. Anyone may reveal, paying for the new accounts, so a
+/// stalled ticket can't hold up the queue. The token record's address
+/// depends on the drawn id, which is fixed once the earlier tickets are
+/// revealed; simulate the reveal to find it. A ticket whose commit slot has
+/// left the SlotHashes window is committed to the current slot instead and
+/// must be revealed again.
+pub fn reveal_random_mint(ctx: Context<RevealRandomMint>, _ticket: u64) -> Result<()> {
+    check_not_cpi()?;
+
+    let nft_pda = &ctx.accounts.nft_pda;
+    let random_mint = &ctx.accounts.random_mint;
+
+    if nft_pda.paused {
+        return Err(error!(ErrorCode::SalePaused));
+    }
+
+    // tickets reveal in order, so the pool each one draws from is fixed by
+    // the tickets before it rather than by when it is revealed
+    if random_mint.ticket != nft_pda.next_reveal_ticket {
+        return Err(error!(ErrorCode::RevealOutOfOrder));
+    }
+
+    if Clock::get()?.slot <= random_mint.commit_slot {
+        return Err(error!(ErrorCode::RevealTooEarly));
+    }
+
+    let collection_pda = &ctx.accounts.collection_pda;
+
+    if &collection_pda.mint != ctx.accounts.collection_mint.key {
+        return Err(error!(ErrorCode::InvalidCollectionMint)); 
+    }
+
+    RemainingTokenIds::check(&ctx.accounts.remaining_token_ids, nft_pda)?;
+
+    let remaining = ctx.accounts.remaining_token_ids.load()?.remaining;
+
+    if remaining == 0 {
+        return Err(error!(ErrorCode::SoldOut));
+    }
+
+    let hash = {
+        let slot_hashes = ctx.accounts.recent_slothashes.try_borrow_data()?;
+
+        find_slot_hash(&slot_hashes, random_mint.commit_slot)
+            .map(|slot_hash| hashv(&[slot_hash, random_mint.key().as_ref()]))
+    };
+
+    // any value known at purchase would let buyers pick their outcome, so
+    // once the commit slot leaves the SlotHashes window the ticket commits
+    // to a new slot and waits again
+    let hash = match hash {
+        Some(hash) => hash,
+        None => {
+            let random_mint = &mut ctx.accounts.random_mint;
+
+            random_mint.commit_slot = Clock::get()?.slot;
+
+            msg!("Recommitted random mint ticket {}.", random_mint.ticket);
+
+            return Ok(());
+        }
+    };
+    let seed = u64::from_le_bytes(hash.to_bytes()[..8].try_into().unwrap());
+
+    let token_id = {
+        let remaining_token_ids = ctx.accounts.remaining_token_ids.to_account_info();
+        let mut data = remaining_token_ids.try_borrow_mut_data()?;
+
+        RemainingTokenIds::draw(&mut data[RemainingTokenIds::SLOTS_OFFSET..], remaining, seed)
+    };
+
+    ctx.accounts.remaining_token_ids.load_mut()?.remaining = remaining - 1;
+
+    {
+        let mint_bitmap = ctx.accounts.mint_bitmap.to_account_info();
+        let data = mint_bitmap.try_borrow_data()?;
+
+        if MintBitmap::is_minted(&data[MintBitmap::BITS_OFFSET..], token_id)? {
+            return Err(error!(ErrorCode::TokenAlreadyMinted));
+        }
+    }
+
+    mint_token(&ctx.accounts.mint_token_accounts(), token_id)?;
+
+    {
+        let mint_bitmap = ctx.accounts.mint_bitmap.to_account_info();
+        let mut data = mint_bitmap.try_borrow_mut_data()?;
+
+        MintBitmap::set_minted(&mut data[MintBitmap::BITS_OFFSET..], token_id)?;
+    }
+
+    // the token id is only known once drawn, so the record is created here
+    // rather than as an Anchor `init` account
+    let token_record = ctx.accounts.token_record.to_account_info();
+    let nft_manager_key = ctx.accounts.nft_manager.key();
+    let token_id_bytes = token_id.to_le_bytes();
+
+    let seeds = [b"token_record".as_ref(), nft_manager_key.as_ref(), token_id_bytes.as_ref()];
+    let (token_record_key, bump) = Pubkey::find_program_address(&seeds, &crate::id());
+
+    if token_record.key != &token_record_key {
+        return Err(error!(ErrorCode::InvalidTokenRecord));
+    }
+
+    if !token_record.data_is_empty() {
+        return Err(error!(ErrorCode::TokenAlreadyMinted));
+    }
+
+    // space: 8 discriminator
+    // + 8 token_id
+    // + 32 mint
+    // + 32 owner
+    // + 8 slot
+    // + 1 bump
+    let signer_seeds = [b"token_record".as_ref(), nft_manager_key.as_ref(), token_id_bytes.as_ref(), &[bump]];
+    create_pda_account(
+        &ctx.accounts.payer.to_account_info(),
+        &token_record,
+        &ctx.accounts.system_program.to_account_info(),
+        89,
+        &signer_seeds,
+    )?;
+
+    let record = TokenRecord {
+        token_id,
+        mint: ctx.accounts.mint.key(),
+        owner: ctx.accounts.random_mint.owner,
+        slot: Clock::get()?.slot,
+        bump,
+    };
+    record.try_serialize(&mut &mut token_record.try_borrow_mut_data()?[..])?;
+
+    ctx.accounts.nft_pda.next_reveal_ticket += 1;
+    ctx.accounts.random_mint.close(ctx.accounts.owner.to_account_info())?;
+
+    msg!("Minted token id {}.", token_id);
+
+    Ok(())
+}
+
+/// Creates a program-owned PDA, topping up rather than failing when
+/// lamports were already sent to its address.
+fn create_pda_account<'info>(
+    payer: &AccountInfo<'info>,
+    account: &AccountInfo<'info>,
+    system_program: &AccountInfo<'info>,
+    space: usize,
+    signer_seeds: &[&[u8]],
+) -> Result<()> {
+    let required = Rent::get()?.minimum_balance(space);
+    let current = account.lamports();
+
+    if current == 0 {
+        return system_program::create_account(
+            CpiContext::new_with_signer(
+                system_program.clone(),
+                system_program::CreateAccount {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+                &[signer_seeds],
+            ),
+            required,
+            space as u64,
+            &crate::id(),
+        );
+    }
+
+    if required > current {
+        system_program::transfer(
+            CpiContext::new(
+                system_program.clone(),
+                system_program::Transfer {
+                    from: payer.clone(),
+                    to: account.clone(),
+                },
+            ),
+            required - current,
+        )?;
+    }
+
+    system_program::allocate(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            system_program::Allocate {
+                account_to_allocate: account.clone(),
+            },
+            &[signer_seeds],
+        ),
+        space as u64,
+    )?;
+
+    system_program::assign(
+        CpiContext::new_with_signer(
+            system_program.clone(),
+            system_program::Assign {
+                account_to_assign: account.clone(),
+            },
+            &[signer_seeds],
+        ),
+        &crate::id(),
+    )
+}
+
+/// Rejects calls made through CPI, where the calling program could inspect
+/// the outcome and revert.
+fn check_not_cpi() -> Result<()> {
+    if get_stack_height() != TRANSACTION_LEVEL_STACK_HEIGHT {
+        return Err(error!(ErrorCode::CpiNotAllowed));
+    }
+
+    Ok(())
+}
+
+/// Finds the hash of `slot` in raw SlotHashes sysvar data: a u64 length
+/// followed by `(slot, hash)` entries, newest first.
+pub fn find_slot_hash(data: &[u8], slot: u64) -> Option<&[u8]> {
+    let len = u64::from_le_bytes(data.get(..8)?.try_into().ok()?) as usize;
+
+    data.get(8..)?
+        .chunks_exact(40)
+        .take(len)
+        .find(|entry| u64::from_le_bytes(entry[..8].try_into().unwrap()) == slot)
+        .map(|entry| &entry[8..])
+}
+
+/// While an allowlist root is set, checks that the payer's
+/// `(pubkey, allocation)` leaf is in the Merkle tree and that the payer has
+/// allowlist mints left, counting this mint against them. An allocation of 0
+/// means no limit.
+fn apply_allowlist(
+    nft_pda: &NftPda,
+    payer: &Pubkey,
+    proof: &[[u8; 32]],
+    allocation: u16,
+    wallet_mints: &mut WalletMints,
+) -> Result<()> {
+    let allowlist_root = match nft_pda.allowlist_root {
+        Some(allowlist_root) => allowlist_root,
+        None => return Ok(()),
+    };
+
+    let leaf = keccak::hashv(&[payer.as_ref(), &allocation.to_le_bytes()]).0;
+    if !verify_merkle_proof(proof, allowlist_root, leaf) {
+        return Err(error!(ErrorCode::NotAllowlisted));
+    }
+
+    if allocation > 0 && wallet_mints.allowlist_count >= allocation {
+        return Err(error!(ErrorCode::WalletMintLimitReached));
+    }
+    wallet_mints.allowlist_count += 1;
+
+    Ok(())
+}
+
+/// Checks that `voucher` is for this collection and the payer, has not
+/// expired, and was signed by the collection's voucher signer in an Ed25519
+/// program instruction placed right before this one.
+fn verify_voucher(
+    nft_pda: &Account<NftPda>,
+    payer: &Pubkey,
+    voucher: &MintVoucher,
+    instructions: &AccountInfo,
+) -> Result<()> {
+    let voucher_signer = nft_pda.voucher_signer.ok_or_else(|| error!(ErrorCode::InvalidVoucher))?;
+
+    // vouchers are signed per collection, so one signer can serve several
+    if voucher.program_id != crate::id() || voucher.nft_pda != nft_pda.key() {
+        return Err(error!(ErrorCode::InvalidVoucher));
+    }
+
+    if &voucher.payer != payer || Clock::get()?.unix_timestamp >= voucher.expiry {
+        return Err(error!(ErrorCode::InvalidVoucher));
+    }
+
+    let current_index = sysvar::instructions::load_current_index_checked(instructions)?;
+    if current_index == 0 {
+        return Err(error!(ErrorCode::InvalidVoucher));
+    }
+
+    let ed25519_ix = sysvar::instructions::load_instruction_at_checked(current_index as usize - 1, instructions)?;
+    if ed25519_ix.program_id != ed25519_program::ID {
+        return Err(error!(ErrorCode::InvalidVoucher));
+    }
+
+    let (public_key, message) = parse_ed25519_instruction(&ed25519_ix.data)
+        .ok_or_else(|| error!(ErrorCode::InvalidVoucher))?;
+
+    if public_key != voucher_signer.as_ref() || message != voucher.try_to_vec()?.as_slice() {
+        return Err(error!(ErrorCode::InvalidVoucher));
+    }
+
+    Ok(())
+}
+
+/// Returns the public key and message of an Ed25519 program instruction that
+/// verifies a single signature with all of its data inline.
+fn parse_ed25519_instruction(data: &[u8]) -> Option<(&[u8], &[u8])> {
+    let read_u16 = |offset: usize| -> Option<u16> {
+        Some(u16::from_le_bytes(data.get(offset..offset + 2)?.try_into().ok()?))
+    };
+
+    // num_signatures, padding, then the signature offsets
+    if *data.first()? != 1 {
+        return None;
+    }
+
+    let signature_instruction_index = read_u16(4)?;
+    let public_key_offset = read_u16(6)? as usize;
+    let public_key_instruction_index = read_u16(8)?;
+    let message_data_offset = read_u16(10)? as usize;
+    let message_data_size = read_u16(12)? as usize;
+    let message_instruction_index = read_u16(14)?;
+
+    let inline = u16::MAX;
+    if signature_instruction_index != inline || public_key_instruction_index != inline || message_instruction_index != inline {
+        return None;
+    }
+
+    Some((
+        data.get(public_key_offset..public_key_offset + 32)?,
+        data.get(message_data_offset..message_data_offset + message_data_size)?,
+    ))
+}
+
+/// Creates the voucher's nonce account so the voucher can't be used again.
+fn consume_voucher_nonce<'info>(
+    accounts: &PaymentAccounts<'_, 'info>,
+    nonce: u64,
+    remaining_accounts: &mut std::slice::Iter<AccountInfo<'info>>,
+) -> Result<()> {
+    let voucher_nonce = next_account_info(remaining_accounts)?;
+    let nft_manager_key = accounts.nft_manager.key();
+    let nonce_bytes = nonce.to_le_bytes();
+
+    let seeds = [b"voucher_nonce".as_ref(), nft_manager_key.as_ref(), nonce_bytes.as_ref()];
+    let (voucher_nonce_key, bump) = Pubkey::find_program_address(&seeds, &crate::id());
+
+    if voucher_nonce.key != &voucher_nonce_key {
+        return Err(error!(ErrorCode::InvalidVoucher));
+    }
+
+    if !voucher_nonce.data_is_empty() {
+        return Err(error!(ErrorCode::VoucherAlreadyUsed));
+    }
+
+    // space: 8 discriminator
+    // + 32 payer
+    // + 1 bump
+    let signer_seeds = [b"voucher_nonce".as_ref(), nft_manager_key.as_ref(), nonce_bytes.as_ref(), &[bump]];
+    create_pda_account(&accounts.payer, voucher_nonce, &accounts.system_program, 41, &signer_seeds)?;
+
+    let record = VoucherNonce {
+        payer: accounts.payer.key(),
+        bump,
+    };
+    record.try_serialize(&mut &mut voucher_nonce.try_borrow_mut_data()?[..])?;
+
+    Ok(())
+}
+
+/// Verifies a Merkle proof built with keccak256 over sorted node pairs.
+pub fn verify_merkle_proof(proof: &[[u8; 32]], root: [u8; 32], leaf: [u8; 32]) -> bool {
+    let computed = proof.iter().fold(leaf, |node, sibling| {
+        if node <= *sibling {
+            keccak::hashv(&[&node, sibling]).0
+        } else {
+            keccak::hashv(&[sibling, &node]).0
+        }
+    });
+
+    computed == root
+}
+
+/// Checks that minting is open under the configured sale phases and that the
+/// payer is within the active phase's per-wallet cap, counting this mint
+/// against it. Returns the price to charge: the active phase's price, or the
+/// collection's pricing mode when no phases are configured.
+fn apply_sale_phase(nft_pda: &NftPda, wallet_mints: &mut WalletMints) -> Result<u64> {
+    let now = Clock::get()?.unix_timestamp;
+
+    if nft_pda.sale_phases.is_empty() {
+        return nft_pda.current_price(now);
+    }
+
+    let phase = nft_pda.sale_phases
+        .iter()
+        .find(|phase| phase.start_ts <= now && now < phase.end_ts)
+        .ok_or_else(|| error!(ErrorCode::NoActiveSalePhase))?;
+
+    let minted = &mut wallet_mints.counts[phase.id as usize];
+    if phase.per_wallet_cap > 0 && *minted >= phase.per_wallet_cap {
+        return Err(error!(ErrorCode::WalletMintLimitReached));
+    }
+    *minted += 1;
+
+    Ok(phase.price)
+}
+
+/// While a soft cap is set, checks that the raise has not failed and records
+/// the payer's escrowed payment so it can be refunded.
+pub fn apply_soft_cap(nft_pda: &NftPda, price: u64, wallet_mints: &mut WalletMints) -> Result<()> {
+    if nft_pda.soft_cap == 0 {
+        return Ok(());
+    }
+
+    if nft_pda.soft_cap_failed(Clock::get()?.unix_timestamp) {
+        return Err(error!(ErrorCode::SoftCapFailed));
+    }
+
+    wallet_mints.escrowed_lamports += price;
+    wallet_mints.escrowed_count += 1;
+
+    Ok(())
+}
+
+/// While a gate collection is set, checks that the payer holds an NFT whose
+/// metadata carries a verified collection equal to it.
+fn verify_token_gate<'info>(
+    nft_pda: &NftPda,
+    payer: &Pubkey,
+    remaining_accounts: &mut std::slice::Iter<AccountInfo<'info>>,
+) -> Result<()> {
+    let gate_collection = match nft_pda.gate_collection {
+        Some(gate_collection) => gate_collection,
+        None => return Ok(()),
+    };
+
+    let gate_token_account = Account::<token::TokenAccount>::try_from(next_account_info(remaining_accounts)?)?;
+    let gate_metadata = Metadata::from_account_info(next_account_info(remaining_accounts)?)?;
+
+    if &gate_token_account.owner != payer || gate_token_account.amount == 0 {
+        return Err(error!(ErrorCode::NotTokenHolder));
+    }
+
+    if gate_metadata.mint != gate_token_account.mint {
+        return Err(error!(ErrorCode::NotTokenHolder));
+    }
+
+    match gate_metadata.collection {
+        Some(collection) if collection.verified && collection.key == gate_collection => Ok(()),
+        _ => Err(error!(ErrorCode::NotTokenHolder)),
+    }
+}
+
+/// Takes the escrow vault from the remaining accounts and checks its address.
+pub fn next_vault<'info>(
+    nft_manager: &Pubkey,
+    remaining_accounts: &mut std::slice::Iter<AccountInfo<'info>>,
+) -> Result<AccountInfo<'info>> {
+    let vault = next_account_info(remaining_accounts)?;
+    let vault_bump = Account::<Vault>::try_from(vault)?.bump;
+    let vault_key = Pubkey::create_program_address(
+        &[b"vault".as_ref(), nft_manager.as_ref(), &[vault_bump]],
+        &crate::id(),
+    ).map_err(|_| error!(ErrorCode::InvalidVault))?;
+
+    if vault.key != &vault_key {
+        return Err(error!(ErrorCode::InvalidVault));
+    }
+
+    Ok(vault.to_account_info())
+}
+
+/// Takes `price` from the payer: lamports into `nft_pda`, or tokens of the
+/// payment mint into the `nft_pda` treasury when one is configured.
+fn collect_payment<'info>(
+    accounts: &PaymentAccounts<'_, 'info>,
+    price: u64,
+    remaining_accounts: &mut std::slice::Iter<AccountInfo<'info>>,
+) -> Result<()> {
+    let nft_pda = accounts.nft_pda;
+
+    match nft_pda.payment_mint {
+        None => {
+            let destination = if nft_pda.soft_cap > 0 {
+                next_vault(accounts.nft_manager.key, remaining_accounts)?
+            } else {
+                nft_pda.to_account_info()
+            };
+
+            msg!("Initiating transfer of {} lamports...", price);
+            system_program::transfer(
+                CpiContext::new(
+                    accounts.system_program.to_account_info(),
+                    system_program::Transfer {
+                        from: accounts.payer.to_account_info(),
+                        to: destination,
+                    }
+                ),
+                price
+            )?;
+        }
+        Some(payment_mint) => {
+            let payer_token_account = next_account_info(remaining_accounts)?;
+            let treasury = next_account_info(remaining_accounts)?;
+
+            if treasury.key != &associated_token::get_associated_token_address(&nft_pda.key(), &payment_mint) {
+                return Err(error!(ErrorCode::InvalidTreasury));
+            }
+
+            msg!("Initiating transfer of {} tokens...", price);
+            token::transfer(
+                CpiContext::new(
+                    accounts.token_program.to_account_info(),
+                    token::Transfer {
+                        from: payer_token_account.to_account_info(),
+                        to: treasury.to_account_info(),
+                        authority: accounts.payer.to_account_info(),
+                    }
+                ),
+                price
+            )?;
+        }
+    }
+
+    Ok(())
+}
+
+/// Burns one whole token from the payer's token account in place of payment,
+/// after checking it is of the redemption mint or a verified member of the
+/// redemption collection.
+fn redeem_token<'info>(
+    accounts: &PaymentAccounts<'_, 'info>,
+    redemption: &Redemption,
+    remaining_accounts: &mut std::slice::Iter<AccountInfo<'info>>,
+) -> Result<()> {
+    let redeemed_token_account_info = next_account_info(remaining_accounts)?;
+    let redeemed_mint_info = next_account_info(remaining_accounts)?;
+    let redeemed_token_account = Account::<token::TokenAccount>::try_from(redeemed_token_account_info)?;
+    let redeemed_mint = Account::<token::Mint>::try_from(redeemed_mint_info)?;
+
+    if &redeemed_token_account.owner != accounts.payer.key || redeemed_token_account.mint != redeemed_mint.key() {
+        return Err(error!(ErrorCode::InvalidRedemptionToken));
+    }
+
+    match *redemption {
+        Redemption::Mint { mint } => {
+            if redeemed_mint.key() != mint {
+                return Err(error!(ErrorCode::InvalidRedemptionToken));
+            }
+        }
+        Redemption::Collection { collection } => {
+            let redeemed_metadata = Metadata::from_account_info(next_account_info(remaining_accounts)?)?;
+
+            if redeemed_metadata.mint != redeemed_mint.key() {
+                return Err(error!(ErrorCode::InvalidRedemptionToken));
+            }
+
+            match redeemed_metadata.collection {
+                Some(redeemed_collection) if redeemed_collection.verified && redeemed_collection.key == collection => {}
+                _ => return Err(error!(ErrorCode::InvalidRedemptionToken)),
+            }
+        }
+    }
+
+    let amount = 10u64
+        .checked_pow(redeemed_mint.decimals as u32)
+        .filter(|amount| redeemed_token_account.amount >= *amount)
+        .ok_or_else(|| error!(ErrorCode::InvalidRedemptionToken))?;
+
+    msg!("Burning redeemed token...");
+    token::burn(
+        CpiContext::new(
+            accounts.token_program.to_account_info(),
+            token::Burn {
+                mint: redeemed_mint_info.to_account_info(),
+                from: redeemed_token_account_info.to_account_info(),
+                authority: accounts.payer.to_account_info(),
+            }
+        ),
+        amount
+    )?;
+
+    Ok(())
+}
+
+/// When a referrer follows the other remaining accounts, pays them the
+/// collection's referral share of `price` out of `nft_pda` and adds it to
+/// their referral record. Referrals are paid in lamports only, so they are
+/// unavailable with a payment mint, a redemption, or while a soft cap
+/// escrows payments.
+fn pay_referrer<'info>(
+    accounts: &PaymentAccounts<'_, 'info>,
+    price: u64,
+    remaining_accounts: &mut std::slice::Iter<AccountInfo<'info>>,
+) -> Result<()> {
+    let referrer = match remaining_accounts.next() {
+        Some(referrer) => referrer,
+        None => return Ok(()),
+    };
+    let referral_record_info = next_account_info(remaining_accounts)?;
+    let nft_pda = accounts.nft_pda;
+
+    if nft_pda.referral_bps == 0
+        || nft_pda.payment_mint.is_some()
+        || nft_pda.redemption.is_some()
+        || nft_pda.soft_cap > 0
+    {
+        return Err(error!(ErrorCode::ReferralUnavailable));
+    }
+
+    if referrer.key == accounts.payer.key {
+        return Err(error!(ErrorCode::InvalidReferrer));
+    }
+
+    let mut referral_record = Account::<ReferralRecord>::try_from(referral_record_info)?;
+    let referral_record_key = Pubkey::create_program_address(
+        &[b"referral".as_ref(), accounts.nft_manager.key.as_ref(), referrer.key.as_ref(), &[referral_record.bump]],
+        &crate::id(),
+    ).map_err(|_| error!(ErrorCode::InvalidReferrer))?;
+
+    if referral_record_info.key != &referral_record_key {
+        return Err(error!(ErrorCode::InvalidReferrer));
+    }
+
+    let share = (price as u128 * nft_pda.referral_bps as u128 / 10_000) as u64;
+
+    msg!("Paying {} lamports to referrer...", share);
+    **nft_pda.to_account_info().try_borrow_mut_lamports()? -= share;
+    **referrer.try_borrow_mut_lamports()? += share;
+
+    referral_record.referred_count += 1;
+    referral_record.total_lamports += share;
+    referral_record.exit(&crate::id())?;
+
+    Ok(())
+}
+
+/// Creates the token, its metadata and master edition for `token_id` in the
+/// owner's wallet and verifies it as a member of the collection.
+pub(crate) fn mint_token(accounts: &MintTokenAccounts, token_id: u64) -> Result<()> {
+    let nft_pda = accounts.nft_pda;
+    let collection_pda = accounts.collection_pda;
+
+    msg!("Creating mint account...");
+    system_program::create_account(
+        CpiContext::new(
+            accounts.token_program.to_account_info(),
+            system_program::CreateAccount {
+                from: accounts.payer.to_account_info(),
+                to: accounts.mint.to_account_info(),
+            },
+        ),
+        10000000, // Lamports
+        82, // Size
+        &accounts.token_program.key(), // Token Program owns the Mint account
+    )?;
+
+    msg!("Initializing mint account...");
+    token::initialize_mint(
+        CpiContext::new(
+            accounts.token_program.to_account_info(),
             token::InitializeMint {
-                mint: ctx.accounts.mint.to_account_info(),
-                rent: ctx.accounts.rent.to_account_info(),
+                mint: accounts.mint.to_account_info(),
+                rent: accounts.rent.to_account_info(),
             },
         ),
         0, // Decimals
-        &ctx.accounts.mint_authority.key(), // authority
-        Some(&ctx.accounts.mint_authority.key()), // freeze authority
+        &accounts.mint_authority.key(), // authority
+        Some(&accounts.mint_authority.key()), // freeze authority
     )?;
 
     msg!("Creating token account...");
     associated_token::create(
         CpiContext::new(
-            ctx.accounts.associated_token_program.to_account_info(),
+            accounts.associated_token_program.to_account_info(),
             associated_token::Create {
-                payer: ctx.accounts.payer.to_account_info(),
-                associated_token: ctx.accounts.token_account.to_account_info(),
-                authority: ctx.accounts.payer.to_account_info(),
-                mint: ctx.accounts.mint.to_account_info(),
-                system_program: ctx.accounts.system_program.to_account_info(),
-                token_program: ctx.accounts.token_program.to_account_info(),
-                rent: ctx.accounts.rent.to_account_info(),
+                payer: accounts.payer.to_account_info(),
+                associated_token: accounts.token_account.to_account_info(),
+                authority: accounts.owner.to_account_info(),
+                mint: accounts.mint.to_account_info(),
+                system_program: accounts.system_program.to_account_info(),
+                token_program: accounts.token_program.to_account_info(),
+                rent: accounts.rent.to_account_info(),
             },
         ),
     )?;
@@ -304,11 +1985,11 @@ pub fn mint(
     msg!("Minting token to token account...");
     token::mint_to(
         CpiContext::new(
-            ctx.accounts.token_program.to_account_info(),
+            accounts.token_program.to_account_info(),
             token::MintTo {
-                mint: ctx.accounts.mint.to_account_info(),
-                to: ctx.accounts.token_account.to_account_info(),
-                authority: ctx.accounts.mint_authority.to_account_info(),
+                mint: accounts.mint.to_account_info(),
+                to: accounts.token_account.to_account_info(),
+                authority: accounts.mint_authority.to_account_info(),
             },
         ),
         1,
@@ -318,12 +1999,11 @@ pub fn mint(
     let symbol = nft_pda.symbol.to_string();
     let uri = nft_pda.base_token_uri.to_string() + &token_id.to_string() + &std::string::ToString::to_string(".json");
 
-    let nft_manager = ctx.accounts.nft_manager.to_account_info();
-    let nft_manager_key = nft_manager.key();
+    let nft_manager_key = accounts.nft_manager.key();
 
     let creators = vec![
         mpl_token_metadata::state::Creator {
-            address: nft_manager_key,
+            address: nft_pda.creator,
             verified: false,
             share: 100,
         },
@@ -337,10 +2017,10 @@ pub fn mint(
     invoke_signed(
         &token_instruction::create_metadata_accounts_v3(
             TOKEN_METADATA_ID, 
-            ctx.accounts.metadata.key(), // metadata_account
-            ctx.accounts.mint.key(),  // mint_account
-            ctx.accounts.mint_authority.key(), // Mint authority
-            ctx.accounts.mint_authority.key(), // Payer
+            accounts.metadata.key(), // metadata_account
+            accounts.mint.key(),  // mint_account
+            accounts.mint_authority.key(), // Mint authority
+            accounts.mint_authority.key(), // Payer
             nft_pda.key(), // Update authority
             name, 
             symbol, 
@@ -354,12 +2034,12 @@ pub fn mint(
             None, // Option<CollectionDetails>
         ),
         &[
-            ctx.accounts.metadata.to_account_info(),
-            ctx.accounts.mint.to_account_info(),
-            ctx.accounts.mint_authority.to_account_info(),
-            ctx.accounts.mint_authority.to_account_info(),
+            accounts.metadata.to_account_info(),
+            accounts.mint.to_account_info(),
+            accounts.mint_authority.to_account_info(),
+            accounts.mint_authority.to_account_info(),
             nft_pda.to_account_info(),
-            ctx.accounts.rent.to_account_info(),
+            accounts.rent.to_account_info(),
         ],
         &[&signer_seeds],
     )?;
@@ -368,22 +2048,22 @@ pub fn mint(
     invoke_signed(
         &token_instruction::create_master_edition_v3(
             TOKEN_METADATA_ID, 
-            ctx.accounts.master_edition.key(), // // (master) edition account
-            ctx.accounts.mint.key(), // mint account
+            accounts.master_edition.key(), // // (master) edition account
+            accounts.mint.key(), // mint account
             nft_pda.key(), // Update authority
-            ctx.accounts.mint_authority.key(), // Mint authority
-            ctx.accounts.metadata.key(), // Metadata
-            ctx.accounts.mint_authority.key(), // Payer
+            accounts.mint_authority.key(), // Mint authority
+            accounts.metadata.key(), // Metadata
+            accounts.mint_authority.key(), // Payer
             Some(0), // max_supply: Option<u64>
         ),
         &[
-            ctx.accounts.master_edition.to_account_info(),
-            ctx.accounts.mint.to_account_info(),
+            accounts.master_edition.to_account_info(),
+            accounts.mint.to_account_info(),
             nft_pda.to_account_info(),
-            ctx.accounts.mint_authority.to_account_info(),
-            ctx.accounts.metadata.to_account_info(),
-            ctx.accounts.mint_authority.to_account_info(),
-            ctx.accounts.rent.to_account_info(),
+            accounts.mint_authority.to_account_info(),
+            accounts.metadata.to_account_info(),
+            accounts.mint_authority.to_account_info(),
+            accounts.rent.to_account_info(),
         ],
         &[&signer_seeds],
     )?;
@@ -396,36 +2076,34 @@ pub fn mint(
     invoke_signed(
         &token_instruction::set_and_verify_sized_collection_item(
             TOKEN_METADATA_ID,
-            ctx.accounts.metadata.key(), // Metadata account
+            accounts.metadata.key(), // Metadata account
             collection_pda.key(), // Collection Update authority
-            ctx.accounts.payer.key(), // payer
-            ctx.accounts.nft_pda.to_account_info().key(), // Update Authority of Collection NFT and NFT
-            ctx.accounts.collection_mint.key(), // Mint of the Collection
-            ctx.accounts.collection_metadata.key(), // Metadata Account of the Collection
-            ctx.accounts.collection_master_edition.key(), // MasterEdition Account of the Collection Token
-            Some(ctx.accounts.collection_authority_record.key()), // Collection authority record
+            accounts.payer.key(), // payer
+            accounts.nft_pda.to_account_info().key(), // Update Authority of Collection NFT and NFT
+            accounts.collection_mint.key(), // Mint of the Collection
+            accounts.collection_metadata.key(), // Metadata Account of the Collection
+            accounts.collection_master_edition.key(), // MasterEdition Account of the Collection Token
+            Some(accounts.collection_authority_record.key()), // Collection authority record
         ),
         &[
-            ctx.accounts.metadata.to_account_info(),
+            accounts.metadata.to_account_info(),
             collection_pda.to_account_info(),
-            ctx.accounts.payer.to_account_info(),
-            ctx.accounts.nft_pda.to_account_info(),
-            ctx.accounts.collection_mint.to_account_info(),
-            ctx.accounts.collection_metadata.to_account_info(),
-            ctx.accounts.collection_master_edition.to_account_info(),
-            ctx.accounts.collection_authority_record.to_account_info(),
+            accounts.payer.to_account_info(),
+            accounts.nft_pda.to_account_info(),
+            accounts.collection_mint.to_account_info(),
+            accounts.collection_metadata.to_account_info(),
+            accounts.collection_master_edition.to_account_info(),
+            accounts.collection_authority_record.to_account_info(),
         ],
         &[&collection_signer_seeds],
     )?;
 
-    msg!("Token mint process completed successfully.");
-
     Ok(())
 }
 
 pub fn set_collection(ctx: Context<SetCollection>) -> Result<()> {
     let nft_pda = &ctx.accounts.nft_pda;
-    if &nft_pda.creator != ctx.accounts.nft_manager.key {
+    if &nft_pda.collection_admin != ctx.accounts.authority.key {
         return Err(error!(ErrorCode::InvalidNftManager));
     }
 
@@ -472,7 +2150,7 @@ pub fn set_and_verify_collection(
 ) -> Result<()> {
     let nft_pda = &ctx.accounts.nft_pda;
 
-    if &nft_pda.creator != ctx.accounts.nft_manager.key {
+    if &nft_pda.collection_admin != ctx.accounts.authority.key {
         return Err(error!(ErrorCode::InvalidNftManager));
     }
 
@@ -515,117 +2193,605 @@ pub fn set_and_verify_collection(
         &[&collection_signer_seeds],
     )?;
 
-  Ok(())
+  Ok(())
+}
+
+pub fn update_metadata_account(
+    ctx: Context<UpdateMetadataAccount>,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Result<()> {
+    let nft_pda = &ctx.accounts.nft_pda;
+
+    if &nft_pda.metadata_admin != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    if nft_pda.multisig_enabled {
+        return Err(error!(ErrorCode::MultisigRequired));
+    }
+
+    update_metadata(
+        nft_pda,
+        &ctx.accounts.nft_manager.to_account_info(),
+        &ctx.accounts.metadata.to_account_info(),
+        name,
+        symbol,
+        uri,
+    )
+}
+
+/// Replaces the data of a metadata account whose update authority is
+/// `nft_pda`.
+pub(crate) fn update_metadata<'info>(
+    nft_pda: &Account<'info, NftPda>,
+    nft_manager: &AccountInfo<'info>,
+    metadata: &AccountInfo<'info>,
+    name: String,
+    symbol: String,
+    uri: String,
+) -> Result<()> {
+    let nft_manager_key = nft_manager.key();
+
+    let creators = vec![
+        mpl_token_metadata::state::Creator {
+            address: nft_pda.creator,
+            verified: false,
+            share: 100,
+        },
+    ];
+
+    let data = DataV2 {
+        name,
+        symbol,
+        uri,
+        seller_fee_basis_points: 200, // seller_fee_basis_points
+        creators: Some(creators),
+        collection: None, // Option<Collection>
+        uses: None, // Option<Uses>
+    };
+
+    let seeds = [b"nft_pda".as_ref(), nft_manager_key.as_ref()];
+    let bump = assert_derivation(&crate::id(), &nft_pda.to_account_info(), &seeds)?;
+    let signer_seeds = [b"nft_pda".as_ref(), nft_manager_key.as_ref(), &[bump]];
+
+    msg!("Updating metadata account...");
+    invoke_signed(
+        &token_instruction::update_metadata_accounts_v2(
+            TOKEN_METADATA_ID, 
+            metadata.key(), // metadata_account
+            nft_pda.to_account_info().key(), // update authority
+            None, // new update authority
+            Some(data), // data
+            None, // primary_sale_happened
+            Some(true), // is_mutable
+        ),
+        &[
+            metadata.to_account_info(),
+            nft_pda.to_account_info(),
+        ],
+        &[&signer_seeds],
+    )?;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+#[instruction(name: String, symbol: String, base_token_uri: String, price_lamports: u64, max_supply: u64)]
+pub struct Initialize<'info> {
+    #[account(mut)]
+    pub initializer: Signer<'info>,
+    // space: 8 discriminator
+    // + 32 creator
+    // + 1 option + 32 pending_creator
+    // + 32 price_admin
+    // + 32 metadata_admin
+    // + 32 treasury_admin
+    // + 32 collection_admin
+    // + 4 name length + 100 name
+    // + 4 name length + 100 symbol
+    // + 4 name length + 200 base_token_uri
+    // + 8 price_lamports
+    // + 8 max_supply
+    // + 8 minted_count
+    // + 1 mint_mode
+    // + 8 next_token_id
+    // + 1 option + 32 payment_mint
+    // + 8 price_tokens
+    // + 4 sale_phases length + 5 * (1 id + 8 start_ts + 8 end_ts + 8 price + 2 per_wallet_cap)
+    // + 1 next_phase_id
+    // + 1 option + 32 allowlist_root
+    // + 1 option + 32 gate_collection
+    // + 1 pricing_mode variant + 40 pricing_mode fields
+    // + 8 soft_cap
+    // + 8 soft_cap_deadline
+    // + 8 raised
+    // + 1 option + 32 voucher_signer
+    // + 1 paused
+    // + 2 referral_bps
+    // + 4 price_overrides length + 8 * (8 start_id + 8 end_id + 8 price)
+    // + 1 option + 1 redemption variant + 32 redemption mint or collection
+    // + 1 multisig_enabled
+    // + 8 next_random_ticket
+    // + 8 next_reveal_ticket
+    // + 8 open_auctions
+    // + 1 bump
+    #[account(
+        init,
+        payer = initializer,
+        space = NftPda::SPACE,
+        seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()],
+        bump,
+    )]
+    pub nft_pda: Account<'info, NftPda>,
+    // space: 8 discriminator
+    // + 32 authority
+    // + 32 mint
+    // + 1 bump
+    #[account(
+        init,
+        payer = initializer,
+        space = 73,
+        seeds = [b"collection_pda".as_ref(), nft_manager.to_account_info().key.as_ref()],
+        bump,
+    )]
+    pub collection_pda: Account<'info, CollectionPda>,
+    // space: 8 discriminator
+    // + 32 nft_pda
+    // + 8 capacity
+    // + 1 bit per token id
+    #[account(
+        init,
+        payer = initializer,
+        space = MintBitmap::space(max_supply),
+        seeds = [b"mint_bitmap".as_ref(), nft_manager.to_account_info().key.as_ref()],
+        bump,
+    )]
+    pub mint_bitmap: AccountLoader<'info, MintBitmap>,
+    // must sign so no one else can claim the PDAs seeded by this key
+    pub nft_manager: Signer<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(max_supply: u64)]
+pub struct MigrateNftPda<'info> {
+    /// CHECK: Still in the legacy layout; checked and rewritten by the instruction
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: UncheckedAccount<'info>,
+    // space: 8 discriminator
+    // + 32 nft_pda
+    // + 8 capacity
+    // + 1 bit per token id
+    #[account(
+        init,
+        payer = authority,
+        space = MintBitmap::space(max_supply),
+        seeds = [b"mint_bitmap".as_ref(), nft_manager.to_account_info().key.as_ref()],
+        bump,
+    )]
+    pub mint_bitmap: AccountLoader<'info, MintBitmap>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct SetMetadata<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPrice<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPriceOverrides<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Withdraw<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    /// CHECK: Only checked for existence
+    #[account(seeds = [b"revenue_split".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub revenue_split: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    /// CHECK: Any account can receive the withdrawn lamports
+    #[account(mut)]
+    pub destination: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRevenueSplit<'info> {
+    #[account(seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    // space: 8 discriminator
+    // + 4 payees length + 5 * (32 address + 2 share_bps)
+    // + 1 bump
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 183,
+        seeds = [b"revenue_split".as_ref(), nft_manager.to_account_info().key.as_ref()],
+        bump,
+    )]
+    pub revenue_split: Account<'info, RevenueSplit>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct Distribute<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(seeds = [b"revenue_split".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub revenue_split: Account<'info, RevenueSplit>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetPaymentMint<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    pub payment_mint: Account<'info, token::Mint>,
+    #[account(
+        init_if_needed,
+        payer = authority,
+        associated_token::mint = payment_mint,
+        associated_token::authority = nft_pda,
+    )]
+    pub treasury: Account<'info, token::TokenAccount>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, token::Token>,
+    pub associated_token_program: Program<'info, associated_token::AssociatedToken>,
+}
+
+#[derive(Accounts)]
+pub struct ClearPaymentMint<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetRedemption<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct WithdrawTokens<'info> {
+    #[account(seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    /// CHECK: Only checked for existence
+    #[account(seeds = [b"revenue_split".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub revenue_split: UncheckedAccount<'info>,
+    #[account(mut, associated_token::mint = payment_mint, associated_token::authority = nft_pda)]
+    pub treasury: Account<'info, token::TokenAccount>,
+    #[account(mut, token::mint = payment_mint)]
+    pub destination: Account<'info, token::TokenAccount>,
+    pub payment_mint: Account<'info, token::Mint>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    pub token_program: Program<'info, token::Token>,
+}
+
+#[derive(Accounts)]
+pub struct DistributeTokens<'info> {
+    #[account(seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(seeds = [b"revenue_split".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub revenue_split: Account<'info, RevenueSplit>,
+    #[account(mut, associated_token::mint = payment_mint, associated_token::authority = nft_pda)]
+    pub treasury: Account<'info, token::TokenAccount>,
+    pub payment_mint: Account<'info, token::Mint>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    pub token_program: Program<'info, token::Token>,
+}
+
+#[derive(Accounts)]
+pub struct SetPricingMode<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetSoftCap<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    // space: 8 discriminator
+    // + 1 bump
+    #[account(
+        init,
+        payer = authority,
+        space = 9,
+        seeds = [b"vault".as_ref(), nft_manager.to_account_info().key.as_ref()],
+        bump,
+    )]
+    pub vault: Account<'info, Vault>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ReleaseEscrow<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut, seeds = [b"vault".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(token_id: u64)]
+pub struct Refund<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(seeds = [b"collection_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub collection_pda: Account<'info, CollectionPda>,
+    #[account(mut, seeds = [b"vault".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub vault: Account<'info, Vault>,
+    #[account(
+        mut,
+        seeds = [b"wallet_mints".as_ref(), nft_manager.to_account_info().key.as_ref(), buyer.key.as_ref()],
+        bump,
+    )]
+    pub wallet_mints: Account<'info, WalletMints>,
+    #[account(
+        seeds = [b"token_record".as_ref(), nft_manager.to_account_info().key.as_ref(), token_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub token_record: Account<'info, TokenRecord>,
+    #[account(mut)]
+    pub buyer: Signer<'info>,
+    #[account(mut)]
+    pub mint: Account<'info, token::Mint>,
+    #[account(mut, token::mint = mint, token::authority = buyer)]
+    pub token_account: Account<'info, token::TokenAccount>,
+    /// CHECK: Deserialized and checked against the mint and collection
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    pub token_program: Program<'info, token::Token>,
+}
+
+#[derive(Accounts)]
+pub struct Pause<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct Unpause<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
 }
 
-pub fn update_metadata_account(
-    ctx: Context<UpdateMetadataAccount>,
-    name: String,
-    symbol: String,
-    uri: String,
-) -> Result<()> {
-    let nft_pda = &ctx.accounts.nft_pda;
+#[derive(Accounts)]
+pub struct SetRole<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
 
-    if &nft_pda.creator != ctx.accounts.nft_manager.key {
-        return Err(error!(ErrorCode::Unauthorized));
-    }
+#[derive(Accounts)]
+pub struct ProposeManager<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
 
-    let nft_manager = ctx.accounts.nft_manager.to_account_info();
-    let nft_manager_key = nft_manager.key();
+#[derive(Accounts)]
+pub struct CancelManagerProposal<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
 
-    let creators = vec![
-        mpl_token_metadata::state::Creator {
-            address: nft_manager_key,
-            verified: false,
-            share: 100,
-        },
-    ];
+#[derive(Accounts)]
+pub struct AcceptManager<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
 
-    let data = DataV2 {
-        name,
-        symbol,
-        uri,
-        seller_fee_basis_points: 200, // seller_fee_basis_points
-        creators: Some(creators),
-        collection: None, // Option<Collection>
-        uses: None, // Option<Uses>
-    };
+#[derive(Accounts)]
+pub struct SetMaxSupply<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(seeds = [b"mint_bitmap".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub mint_bitmap: AccountLoader<'info, MintBitmap>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
 
-    let seeds = [b"nft_pda".as_ref(), nft_manager_key.as_ref()];
-    let bump = assert_derivation(&crate::id(), &nft_pda.to_account_info(), &seeds)?;
-    let signer_seeds = [b"nft_pda".as_ref(), nft_manager_key.as_ref(), &[bump]];
+#[derive(Accounts)]
+pub struct SetMintMode<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
 
-    msg!("Updating metadata account...");
-    invoke_signed(
-        &token_instruction::update_metadata_accounts_v2(
-            TOKEN_METADATA_ID, 
-            ctx.accounts.metadata.key(), // metadata_account
-            nft_pda.to_account_info().key(), // update authority
-            None, // new update authority
-            Some(data), // data
-            None, // primary_sale_happened
-            Some(true), // is_mutable
-        ),
-        &[
-            ctx.accounts.metadata.to_account_info(),
-            nft_pda.to_account_info(),
-        ],
-        &[&signer_seeds],
-    )?;
+#[derive(Accounts)]
+pub struct SetAllowlistRoot<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
 
-    Ok(())
+#[derive(Accounts)]
+pub struct SetGateCollection<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-pub struct Initialize<'info> {
+pub struct SetVoucherSigner<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
     #[account(mut)]
-    pub initializer: Signer<'info>,
-    // space: 8 discriminator
-    // + 32 creator
-    // + 4 name length + 100 name
-    // + 4 name length + 100 symbol
-    // + 4 name length + 200 base_token_uri
-    // + 1 bump
-    #[account(
-        init,
-        payer = initializer,
-        space = 453,
-        seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()],
-        bump,
-    )]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct SetReferralBps<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RegisterReferrer<'info> {
+    #[account(seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
     pub nft_pda: Account<'info, NftPda>,
     // space: 8 discriminator
-    // + 32 authority
-    // + 32 mint
+    // + 32 referrer
+    // + 8 referred_count
+    // + 8 total_lamports
     // + 1 bump
     #[account(
         init,
-        payer = initializer,
-        space = 73,
-        seeds = [b"collection_pda".as_ref(), nft_manager.to_account_info().key.as_ref()],
+        payer = referrer,
+        space = 57,
+        seeds = [b"referral".as_ref(), nft_manager.to_account_info().key.as_ref(), referrer.key().as_ref()],
         bump,
     )]
-    pub collection_pda: Account<'info, CollectionPda>,
-    /// CHECK: We're about to create this with Anchor
+    pub referral_record: Account<'info, ReferralRecord>,
     #[account(mut)]
+    pub referrer: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
     pub nft_manager: UncheckedAccount<'info>,
     pub system_program: Program<'info, System>,
 }
 
 #[derive(Accounts)]
-pub struct SetMetadata<'info> {
+pub struct AddSalePhase<'info> {
     #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
     pub nft_pda: Account<'info, NftPda>,
     #[account(mut)]
-    pub nft_manager: Signer<'info>,
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
-pub struct SetPrice<'info> {
+pub struct EditSalePhase<'info> {
     #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
     pub nft_pda: Account<'info, NftPda>,
     #[account(mut)]
-    pub nft_manager: Signer<'info>,
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct RemoveSalePhase<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+pub struct FreeTokenIds<'info> {
+    #[account(seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(seeds = [b"mint_bitmap".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub mint_bitmap: AccountLoader<'info, MintBitmap>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
 }
 
 #[derive(Accounts)]
@@ -647,6 +2813,7 @@ pub struct MintCollection<'info> {
     pub token_account: UncheckedAccount<'info>,
     #[account(mut)]
     pub mint_authority: Signer<'info>,
+    pub authority: Signer<'info>,
     /// CHECK: We're about to create this with Anchor
     #[account(mut)]
     pub nft_manager: UncheckedAccount<'info>,
@@ -659,11 +2826,234 @@ pub struct MintCollection<'info> {
 }
 
 #[derive(Accounts)]
+#[instruction(token_id: u64)]
 pub struct MintNft<'info> {
     #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
     pub nft_pda: Account<'info, NftPda>,
     #[account(mut, seeds = [b"collection_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
     pub collection_pda: Account<'info, CollectionPda>,
+    // space: 8 discriminator
+    // + 8 token_id
+    // + 32 mint
+    // + 32 owner
+    // + 8 slot
+    // + 1 bump
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 89,
+        seeds = [b"token_record".as_ref(), nft_manager.to_account_info().key.as_ref(), nft_pda.resolve_token_id(token_id).to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub token_record: Account<'info, TokenRecord>,
+    // space: 8 discriminator
+    // + 2 * 16 counts
+    // + 2 allowlist_count
+    // + 8 escrowed_lamports
+    // + 2 escrowed_count
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 52,
+        seeds = [b"wallet_mints".as_ref(), nft_manager.to_account_info().key.as_ref(), payer.key.as_ref()],
+        bump,
+    )]
+    pub wallet_mints: Account<'info, WalletMints>,
+    #[account(mut, seeds = [b"mint_bitmap".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub mint_bitmap: AccountLoader<'info, MintBitmap>,
+    /// CHECK: We're about to create this with Metaplex
+    #[account(mut)]
+    pub metadata: UncheckedAccount<'info>,
+    /// CHECK: We're about to create this with Metaplex
+    #[account(mut)]
+    pub master_edition: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub mint: Signer<'info>,
+    /// CHECK: We're about to create this with Anchor
+    #[account(mut)]
+    pub token_account: UncheckedAccount<'info>,
+    /// CHECK: We're about to create this with Anchor
+    #[account(mut)]
+    pub mint_authority: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: We're about to create this with Anchor
+    #[account(mut)]
+    pub nft_manager: UncheckedAccount<'info>,
+    /// CHECK: We're about to create this with Anchor
+    pub collection_mint: UncheckedAccount<'info>,
+    /// CHECK: We're about to create this with Metaplex
+    #[account(mut)]
+    pub collection_metadata: UncheckedAccount<'info>,
+    /// CHECK: We're about to create this with Metaplex
+    #[account(mut)]
+    pub collection_master_edition: UncheckedAccount<'info>,
+    /// CHECK: We're about to create this with Metaplex
+    pub collection_authority_record: UncheckedAccount<'info>,
+    /// CHECK: Address is checked; read to verify mint vouchers
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    pub rent: Sysvar<'info, Rent>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, token::Token>,
+    pub associated_token_program: Program<'info, associated_token::AssociatedToken>,
+    /// CHECK: Metaplex will check this
+    pub token_metadata_program: UncheckedAccount<'info>,
+}
+
+impl<'info> MintNft<'info> {
+    fn payment_accounts(&self) -> PaymentAccounts<'_, 'info> {
+        PaymentAccounts {
+            nft_pda: &self.nft_pda,
+            payer: self.payer.to_account_info(),
+            nft_manager: self.nft_manager.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+        }
+    }
+
+    fn mint_token_accounts(&self) -> MintTokenAccounts<'_, 'info> {
+        MintTokenAccounts {
+            nft_pda: &self.nft_pda,
+            collection_pda: &self.collection_pda,
+            metadata: self.metadata.to_account_info(),
+            master_edition: self.master_edition.to_account_info(),
+            mint: self.mint.to_account_info(),
+            token_account: self.token_account.to_account_info(),
+            mint_authority: self.mint_authority.to_account_info(),
+            payer: self.payer.to_account_info(),
+            owner: self.payer.to_account_info(),
+            nft_manager: self.nft_manager.to_account_info(),
+            collection_mint: self.collection_mint.to_account_info(),
+            collection_metadata: self.collection_metadata.to_account_info(),
+            collection_master_edition: self.collection_master_edition.to_account_info(),
+            collection_authority_record: self.collection_authority_record.to_account_info(),
+            rent: self.rent.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            associated_token_program: self.associated_token_program.to_account_info(),
+        }
+    }
+}
+
+#[derive(Accounts)]
+pub struct InitializeRemainingTokenIds<'info> {
+    #[account(seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(seeds = [b"mint_bitmap".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub mint_bitmap: AccountLoader<'info, MintBitmap>,
+    // space: 8 discriminator
+    // + 32 nft_pda
+    // + 8 remaining
+    // + 4 per token id, grown by `grow_remaining_token_ids` past what one
+    // instruction can allocate
+    #[account(
+        init,
+        payer = authority,
+        space = RemainingTokenIds::space(nft_pda.max_supply).min(MAX_PERMITTED_DATA_INCREASE),
+        seeds = [b"remaining_token_ids".as_ref(), nft_manager.to_account_info().key.as_ref()],
+        bump,
+    )]
+    pub remaining_token_ids: AccountLoader<'info, RemainingTokenIds>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct GrowRemainingTokenIds<'info> {
+    #[account(seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    /// CHECK: Only resized here; the header is checked when it is drawn from
+    #[account(mut, seeds = [b"remaining_token_ids".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub remaining_token_ids: UncheckedAccount<'info>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct MintRandomNft<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    // space: 8 discriminator
+    // + 8 ticket
+    // + 32 owner
+    // + 8 commit_slot
+    // + 1 bump
+    #[account(
+        init,
+        payer = payer,
+        space = 57,
+        seeds = [b"random_mint".as_ref(), nft_manager.to_account_info().key.as_ref(), nft_pda.next_random_ticket.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub random_mint: Account<'info, RandomMint>,
+    #[account(seeds = [b"remaining_token_ids".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub remaining_token_ids: AccountLoader<'info, RemainingTokenIds>,
+    // space: 8 discriminator
+    // + 2 * 16 counts
+    // + 2 allowlist_count
+    // + 8 escrowed_lamports
+    // + 2 escrowed_count
+    #[account(
+        init_if_needed,
+        payer = payer,
+        space = 52,
+        seeds = [b"wallet_mints".as_ref(), nft_manager.to_account_info().key.as_ref(), payer.key.as_ref()],
+        bump,
+    )]
+    pub wallet_mints: Account<'info, WalletMints>,
+    #[account(mut)]
+    pub payer: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    /// CHECK: Address is checked; read to verify mint vouchers
+    #[account(address = sysvar::instructions::ID)]
+    pub instructions: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+    pub token_program: Program<'info, token::Token>,
+}
+
+impl<'info> MintRandomNft<'info> {
+    fn payment_accounts(&self) -> PaymentAccounts<'_, 'info> {
+        PaymentAccounts {
+            nft_pda: &self.nft_pda,
+            payer: self.payer.to_account_info(),
+            nft_manager: self.nft_manager.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+        }
+    }
+}
+
+#[derive(Accounts)]
+#[instruction(ticket: u64)]
+pub struct RevealRandomMint<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut, seeds = [b"collection_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub collection_pda: Account<'info, CollectionPda>,
+    #[account(mut, seeds = [b"mint_bitmap".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub mint_bitmap: AccountLoader<'info, MintBitmap>,
+    #[account(mut, seeds = [b"remaining_token_ids".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub remaining_token_ids: AccountLoader<'info, RemainingTokenIds>,
+    #[account(
+        mut,
+        seeds = [b"random_mint".as_ref(), nft_manager.to_account_info().key.as_ref(), ticket.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub random_mint: Account<'info, RandomMint>,
+    /// CHECK: Address is checked against the ticket
+    #[account(mut, address = random_mint.owner)]
+    pub owner: UncheckedAccount<'info>,
+    /// CHECK: Derived from the drawn token id and created in the handler
+    #[account(mut)]
+    pub token_record: UncheckedAccount<'info>,
     /// CHECK: We're about to create this with Metaplex
     #[account(mut)]
     pub metadata: UncheckedAccount<'info>,
@@ -693,6 +3083,9 @@ pub struct MintNft<'info> {
     pub collection_master_edition: UncheckedAccount<'info>,
     /// CHECK: We're about to create this with Metaplex
     pub collection_authority_record: UncheckedAccount<'info>,
+    /// CHECK: Address is checked; read raw since SlotHashes is too large to deserialize
+    #[account(address = sysvar::slot_hashes::ID)]
+    pub recent_slothashes: UncheckedAccount<'info>,
     pub rent: Sysvar<'info, Rent>,
     pub system_program: Program<'info, System>,
     pub token_program: Program<'info, token::Token>,
@@ -701,6 +3094,64 @@ pub struct MintNft<'info> {
     pub token_metadata_program: UncheckedAccount<'info>,
 }
 
+impl<'info> RevealRandomMint<'info> {
+    fn mint_token_accounts(&self) -> MintTokenAccounts<'_, 'info> {
+        MintTokenAccounts {
+            nft_pda: &self.nft_pda,
+            collection_pda: &self.collection_pda,
+            metadata: self.metadata.to_account_info(),
+            master_edition: self.master_edition.to_account_info(),
+            mint: self.mint.to_account_info(),
+            token_account: self.token_account.to_account_info(),
+            mint_authority: self.mint_authority.to_account_info(),
+            payer: self.payer.to_account_info(),
+            owner: self.owner.to_account_info(),
+            nft_manager: self.nft_manager.to_account_info(),
+            collection_mint: self.collection_mint.to_account_info(),
+            collection_metadata: self.collection_metadata.to_account_info(),
+            collection_master_edition: self.collection_master_edition.to_account_info(),
+            collection_authority_record: self.collection_authority_record.to_account_info(),
+            rent: self.rent.to_account_info(),
+            system_program: self.system_program.to_account_info(),
+            token_program: self.token_program.to_account_info(),
+            associated_token_program: self.associated_token_program.to_account_info(),
+        }
+    }
+}
+
+/// Accounts the payment, voucher and referral steps of a mint use.
+pub(crate) struct PaymentAccounts<'a, 'info> {
+    pub nft_pda: &'a Account<'info, NftPda>,
+    pub payer: AccountInfo<'info>,
+    pub nft_manager: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+}
+
+/// Accounts shared by every instruction that mints a token. The payer funds
+/// the new accounts and the token lands in the owner's associated token
+/// account.
+pub(crate) struct MintTokenAccounts<'a, 'info> {
+    pub nft_pda: &'a Account<'info, NftPda>,
+    pub collection_pda: &'a Account<'info, CollectionPda>,
+    pub metadata: AccountInfo<'info>,
+    pub master_edition: AccountInfo<'info>,
+    pub mint: AccountInfo<'info>,
+    pub token_account: AccountInfo<'info>,
+    pub mint_authority: AccountInfo<'info>,
+    pub payer: AccountInfo<'info>,
+    pub owner: AccountInfo<'info>,
+    pub nft_manager: AccountInfo<'info>,
+    pub collection_mint: AccountInfo<'info>,
+    pub collection_metadata: AccountInfo<'info>,
+    pub collection_master_edition: AccountInfo<'info>,
+    pub collection_authority_record: AccountInfo<'info>,
+    pub rent: AccountInfo<'info>,
+    pub system_program: AccountInfo<'info>,
+    pub token_program: AccountInfo<'info>,
+    pub associated_token_program: AccountInfo<'info>,
+}
+
 #[derive(Accounts)]
 pub struct SetCollection<'info> {
     #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
@@ -719,6 +3170,7 @@ pub struct SetCollection<'info> {
     /// CHECK: We're about to create this with Anchor
     #[account(mut)]
     pub collection_authority_record: UncheckedAccount<'info>,
+    pub authority: Signer<'info>,
     /// CHECK: We're about to create this with Anchor
     #[account(mut)]
     pub nft_manager: UncheckedAccount<'info>,
@@ -739,6 +3191,7 @@ pub struct SetAndVerifyCollection<'info> {
     pub collection_pda: Account<'info, CollectionPda>,
     #[account(mut)]
     pub payer: Signer<'info>,
+    pub authority: Signer<'info>,
     /// CHECK: We're about to create this with Anchor
     #[account(mut)]
     pub nft_manager: UncheckedAccount<'info>,
@@ -766,23 +3219,345 @@ pub struct UpdateMetadataAccount<'info> {
     #[account(mut)]
     pub metadata: UncheckedAccount<'info>,
     #[account(mut)]
-    pub nft_manager: Signer<'info>,
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
     pub rent: Sysvar<'info, Rent>,
     pub system_program: Program<'info, System>,
     /// CHECK: Metaplex will check this
     pub token_metadata_program: UncheckedAccount<'info>,
 }
 
+/// `NftPda` as the first release of the program wrote it.
+#[derive(AnchorDeserialize)]
+pub struct LegacyNftPda {
+    pub creator: Pubkey,
+    pub name: String,
+    pub symbol: String,
+    pub base_token_uri: String,
+    pub price_lamports: u64,
+    pub bump: u8,
+}
+
+impl LegacyNftPda {
+    pub const SPACE: usize = 453;
+    // the legacy `mint` only accepted token ids 1 and 2
+    pub const MAX_TOKEN_ID: u64 = 2;
+}
+
 #[account]
 pub struct NftPda {
+    /// Owner of the collection: assigns the admin roles and runs the admin
+    /// instructions no role covers. Starts as the `nft_manager` the PDAs are
+    /// seeded by and moves with `propose_manager`/`accept_manager`.
     pub creator: Pubkey,
+    pub pending_creator: Option<Pubkey>,
+    /// Changes prices, pricing modes, sale phases, the payment mint, the
+    /// voucher signer and redemptions.
+    pub price_admin: Pubkey,
+    /// Changes the collection name, symbol, URI and token metadata.
+    pub metadata_admin: Pubkey,
+    /// Moves funds out and configures payouts and the soft cap.
+    pub treasury_admin: Pubkey,
+    /// Mints and sets the collection NFT.
+    pub collection_admin: Pubkey,
     pub name: String,
     pub symbol: String,
     pub base_token_uri: String,
     pub price_lamports: u64,
+    pub max_supply: u64,
+    pub minted_count: u64,
+    pub mint_mode: MintMode,
+    pub next_token_id: u64,
+    pub payment_mint: Option<Pubkey>,
+    pub price_tokens: u64,
+    pub sale_phases: Vec<SalePhase>,
+    pub next_phase_id: u8,
+    pub allowlist_root: Option<[u8; 32]>,
+    pub gate_collection: Option<Pubkey>,
+    pub pricing_mode: PricingMode,
+    /// Lamports that must be raised by `soft_cap_deadline`, 0 when disabled.
+    pub soft_cap: u64,
+    pub soft_cap_deadline: i64,
+    pub raised: u64,
+    pub voucher_signer: Option<Pubkey>,
+    pub paused: bool,
+    /// Share of each mint price paid to the buyer's referrer.
+    pub referral_bps: u16,
+    pub price_overrides: Vec<PriceOverride>,
+    /// When set, buyers burn a token instead of paying.
+    pub redemption: Option<Redemption>,
+    /// When set, `set_price`, `set_metadata`, `update_metadata_account` and
+    /// token price changes only go through approved multisig proposals.
+    pub multisig_enabled: bool,
+    /// Ticket numbers of the next random mint to queue and to reveal.
+    pub next_random_ticket: u64,
+    pub next_reveal_ticket: u64,
+    /// Auctions created and not yet settled, cancelled or refunded.
+    pub open_auctions: u64,
     pub bump: u8,
 }
 
+impl NftPda {
+    // broken down in `Initialize`
+    pub const SPACE: usize = 1250;
+    pub const MAX_SALE_PHASES: usize = 5;
+    pub const MAX_PRICE_OVERRIDES: usize = 8;
+
+    /// Returns the mint price in lamports, or in units of the payment mint
+    /// when one is configured.
+    pub fn price(&self) -> u64 {
+        match self.payment_mint {
+            None => self.price_lamports,
+            Some(_) => self.price_tokens,
+        }
+    }
+
+    pub fn record_mint(&mut self, price: u64) {
+        self.minted_count += 1;
+        if self.soft_cap > 0 {
+            self.raised += price;
+        }
+    }
+
+    pub fn soft_cap_failed(&self, now: i64) -> bool {
+        now >= self.soft_cap_deadline && self.raised < self.soft_cap
+    }
+
+    /// Returns the price at `now` under the collection's pricing mode.
+    pub fn current_price(&self, now: i64) -> Result<u64> {
+        self.pricing_mode.price(self.price(), self.minted_count, now)
+    }
+
+    /// Checks that there are at most `MAX_PRICE_OVERRIDES` overrides, each
+    /// covering a non-empty range of token ids that no other override covers.
+    fn validate_price_overrides(price_overrides: &[PriceOverride]) -> Result<()> {
+        if price_overrides.len() > Self::MAX_PRICE_OVERRIDES {
+            return Err(error!(ErrorCode::InvalidPriceOverride));
+        }
+
+        for (index, price_override) in price_overrides.iter().enumerate() {
+            if price_override.start_id == 0 || price_override.end_id < price_override.start_id {
+                return Err(error!(ErrorCode::InvalidPriceOverride));
+            }
+
+            let overlaps = price_overrides[..index].iter().any(|other| {
+                other.start_id <= price_override.end_id && price_override.start_id <= other.end_id
+            });
+            if overlaps {
+                return Err(error!(ErrorCode::InvalidPriceOverride));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Returns the override price for `token_id`, or `price` when no
+    /// override covers it.
+    pub fn token_price(&self, token_id: u64, price: u64) -> u64 {
+        self.price_overrides
+            .iter()
+            .find(|price_override| price_override.start_id <= token_id && token_id <= price_override.end_id)
+            .map_or(price, |price_override| price_override.price)
+    }
+
+    /// Returns the token id a mint will use: the caller's choice in explicit
+    /// mode, or the on-chain counter in sequential mode. Random mode mints
+    /// through `mint_random` instead.
+    pub fn resolve_token_id(&self, token_id: u64) -> u64 {
+        match self.mint_mode {
+            MintMode::Explicit | MintMode::Random => token_id,
+            MintMode::Sequential => self.next_token_id,
+        }
+    }
+
+    /// Checks that `phase` starts in the future and does not overlap any
+    /// other phase.
+    fn validate_sale_phase(&self, phase: &SalePhase, now: i64) -> Result<()> {
+        if phase.start_ts <= now || phase.end_ts <= phase.start_ts {
+            return Err(error!(ErrorCode::InvalidSalePhase));
+        }
+
+        let overlaps = self.sale_phases.iter().any(|other| {
+            other.id != phase.id && other.start_ts < phase.end_ts && phase.start_ts < other.end_ts
+        });
+        if overlaps {
+            return Err(error!(ErrorCode::InvalidSalePhase));
+        }
+
+        Ok(())
+    }
+
+    /// Finds a phase that has not started yet and so may still be changed.
+    fn pending_sale_phase_index(&self, phase_id: u8, now: i64) -> Result<usize> {
+        let index = self.sale_phases
+            .iter()
+            .position(|phase| phase.id == phase_id)
+            .ok_or_else(|| error!(ErrorCode::InvalidSalePhase))?;
+
+        if self.sale_phases[index].start_ts <= now {
+            return Err(error!(ErrorCode::SalePhaseStarted));
+        }
+
+        Ok(index)
+    }
+}
+
+/// Prices are in lamports, or in units of the payment mint when one is set.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum PricingMode {
+    /// Charge the price set with `set_price` or `set_payment_mint`.
+    Fixed,
+    /// Starting at `start_ts`, drop the price by `decay_amount` every
+    /// `decay_interval` seconds until it reaches `floor_price`.
+    DutchAuction {
+        start_price: u64,
+        floor_price: u64,
+        start_ts: i64,
+        decay_amount: u64,
+        decay_interval: i64,
+    },
+    /// Charge `base_price + increment * minted_count`.
+    LinearCurve {
+        base_price: u64,
+        increment: u64,
+    },
+    /// Charge `base_price * (1 + growth_bps / 10,000) ^ minted_count`.
+    ExponentialCurve {
+        base_price: u64,
+        growth_bps: u16,
+    },
+}
+
+impl PricingMode {
+    // fixed-point scale for the exponential curve's growth factor
+    const CURVE_SCALE: u128 = 1_000_000_000_000;
+
+    fn validate(&self) -> Result<()> {
+        match *self {
+            PricingMode::DutchAuction { start_price, floor_price, decay_interval, .. } => {
+                if decay_interval <= 0 || floor_price > start_price {
+                    return Err(error!(ErrorCode::InvalidPricingMode));
+                }
+                Ok(())
+            }
+            PricingMode::Fixed | PricingMode::LinearCurve { .. } | PricingMode::ExponentialCurve { .. } => Ok(()),
+        }
+    }
+
+    /// Returns the price of the next mint given the collection's fixed price,
+    /// the number of tokens minted so far and the current unix timestamp.
+    /// Clients can call this to quote the exact price `mint` will charge.
+    pub fn price(&self, fixed_price: u64, minted_count: u64, now: i64) -> Result<u64> {
+        match *self {
+            PricingMode::Fixed => Ok(fixed_price),
+            PricingMode::DutchAuction { start_price, floor_price, start_ts, decay_amount, decay_interval } => {
+                let steps = (now.saturating_sub(start_ts).max(0) / decay_interval) as u64;
+                Ok(start_price
+                    .saturating_sub(steps.saturating_mul(decay_amount))
+                    .max(floor_price))
+            }
+            PricingMode::LinearCurve { base_price, increment } => {
+                increment
+                    .checked_mul(minted_count)
+                    .and_then(|step| step.checked_add(base_price))
+                    .ok_or_else(|| error!(ErrorCode::PriceOverflow))
+            }
+            PricingMode::ExponentialCurve { base_price, growth_bps } => {
+                let factor = Self::growth_factor(growth_bps, minted_count)
+                    .ok_or_else(|| error!(ErrorCode::PriceOverflow))?;
+                (base_price as u128)
+                    .checked_mul(factor)
+                    .map(|price| price / Self::CURVE_SCALE)
+                    .and_then(|price| u64::try_from(price).ok())
+                    .ok_or_else(|| error!(ErrorCode::PriceOverflow))
+            }
+        }
+    }
+
+    /// `(1 + growth_bps / 10,000) ^ exponent` in `CURVE_SCALE` fixed point,
+    /// by square-and-multiply.
+    fn growth_factor(growth_bps: u16, exponent: u64) -> Option<u128> {
+        let mut factor = Self::CURVE_SCALE;
+        let mut base = Self::CURVE_SCALE * (10_000 + growth_bps as u128) / 10_000;
+        let mut exponent = exponent;
+
+        while exponent > 0 {
+            if exponent & 1 == 1 {
+                factor = factor.checked_mul(base)? / Self::CURVE_SCALE;
+            }
+            exponent >>= 1;
+            if exponent > 0 {
+                base = base.checked_mul(base)? / Self::CURVE_SCALE;
+            }
+        }
+
+        Some(factor)
+    }
+}
+
+/// Off-chain authorization for a single mint, signed by the voucher signer.
+/// The signed message is the Borsh serialization of this struct; the program
+/// id and `nft_pda` tie it to one collection.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct MintVoucher {
+    pub program_id: Pubkey,
+    pub nft_pda: Pubkey,
+    pub payer: Pubkey,
+    /// Price in lamports, or in units of the payment mint when one is set.
+    pub price: u64,
+    pub expiry: i64,
+    pub nonce: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct SalePhase {
+    pub id: u8,
+    pub start_ts: i64,
+    pub end_ts: i64,
+    /// Price in lamports, or in units of the payment mint when one is set.
+    pub price: u64,
+    /// Maximum mints per wallet during this phase, 0 for no limit.
+    pub per_wallet_cap: u16,
+}
+
+/// Admin roles the `creator` can hand to other keys with `set_role`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Role {
+    Price,
+    Metadata,
+    Treasury,
+    Collection,
+}
+
+/// The token a buyer burns to mint under a redemption: one whole token of
+/// `mint`, or an NFT verified as a member of `collection`.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum Redemption {
+    Mint { mint: Pubkey },
+    Collection { collection: Pubkey },
+}
+
+/// Price for the token ids `start_id..=end_id`, taking precedence over the
+/// sale phase and pricing mode.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy)]
+pub struct PriceOverride {
+    pub start_id: u64,
+    pub end_id: u64,
+    /// Price in lamports, or in units of the payment mint when one is set.
+    pub price: u64,
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone, Copy, PartialEq, Eq)]
+pub enum MintMode {
+    /// The buyer passes the token id to mint.
+    Explicit,
+    /// The program assigns the next token id; the `token_id` argument is ignored.
+    Sequential,
+    /// `mint_random` draws an unminted token id from `RemainingTokenIds`.
+    Random,
+}
+
 #[account]
 pub struct CollectionPda {
     pub authority: Pubkey,
@@ -790,6 +3565,195 @@ pub struct CollectionPda {
     pub bump: u8,
 }
 
+#[account]
+pub struct RevenueSplit {
+    pub payees: Vec<Payee>,
+    pub bump: u8,
+}
+
+impl RevenueSplit {
+    pub const MAX_PAYEES: usize = 5;
+}
+
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub struct Payee {
+    pub address: Pubkey,
+    pub share_bps: u16,
+}
+
+/// Number of tokens a wallet has minted in each sale phase, indexed by phase
+/// id, and through the allowlist, plus what it has paid into the soft cap
+/// escrow.
+#[account]
+pub struct WalletMints {
+    pub counts: [u16; 16],
+    pub allowlist_count: u16,
+    pub escrowed_lamports: u64,
+    pub escrowed_count: u16,
+}
+
+impl WalletMints {
+    pub const MAX_PHASE_IDS: usize = 16;
+}
+
+/// Holds soft cap payments until the raise succeeds or buyers are refunded.
+#[account]
+pub struct Vault {
+    pub bump: u8,
+}
+
+/// Marks a voucher nonce as used.
+#[account]
+pub struct VoucherNonce {
+    pub payer: Pubkey,
+    pub bump: u8,
+}
+
+/// Running totals of the mints a referrer has brought in.
+#[account]
+pub struct ReferralRecord {
+    pub referrer: Pubkey,
+    pub referred_count: u64,
+    pub total_lamports: u64,
+    pub bump: u8,
+}
+
+/// A paid random mint waiting for `reveal_random_mint` to draw its token id.
+#[account]
+pub struct RandomMint {
+    pub ticket: u64,
+    pub owner: Pubkey,
+    pub commit_slot: u64,
+    pub bump: u8,
+}
+
+#[account]
+pub struct TokenRecord {
+    pub token_id: u64,
+    pub mint: Pubkey,
+    pub owner: Pubkey,
+    pub slot: u64,
+    pub bump: u8,
+}
+
+/// Header of the mint bitmap; one bit per token id follows it in the
+/// account data, with bit `token_id - 1` set once that id is minted.
+#[account(zero_copy)]
+pub struct MintBitmap {
+    pub nft_pda: Pubkey,
+    pub capacity: u64,
+}
+
+impl MintBitmap {
+    pub const BITS_OFFSET: usize = 8 + std::mem::size_of::<MintBitmap>();
+    // return data is capped at 1024 bytes
+    pub const MAX_FREE_TOKEN_IDS: u8 = 127;
+
+    // `u64::div_ceil` is newer than the BPF toolchain
+    #[allow(clippy::manual_div_ceil)]
+    pub fn space(capacity: u64) -> usize {
+        Self::BITS_OFFSET + ((capacity + 7) / 8) as usize
+    }
+
+    /// Byte index and mask of a token id's bit, or `InvalidTokenId` if the
+    /// bitmap has no bit for it.
+    fn bit(bits: &[u8], token_id: u64) -> Result<(usize, u8)> {
+        let index = token_id
+            .checked_sub(1)
+            .map(|index| index as usize)
+            .filter(|index| index / 8 < bits.len())
+            .ok_or(error!(ErrorCode::InvalidTokenId))?;
+
+        Ok((index / 8, 1 << (index % 8)))
+    }
+
+    pub fn is_minted(bits: &[u8], token_id: u64) -> Result<bool> {
+        let (byte, mask) = Self::bit(bits, token_id)?;
+        Ok(bits[byte] & mask != 0)
+    }
+
+    pub fn set_minted(bits: &mut [u8], token_id: u64) -> Result<()> {
+        let (byte, mask) = Self::bit(bits, token_id)?;
+        bits[byte] |= mask;
+        Ok(())
+    }
+
+    pub fn clear_minted(bits: &mut [u8], token_id: u64) -> Result<()> {
+        let (byte, mask) = Self::bit(bits, token_id)?;
+        bits[byte] &= !mask;
+        Ok(())
+    }
+
+    /// Whether any token id above `token_id` is minted.
+    pub fn any_minted_above(bits: &[u8], token_id: u64) -> bool {
+        // bit `i` stands for token id `i + 1`, so ids above `token_id` start at bit `token_id`
+        let byte = (token_id / 8) as usize;
+
+        match bits.get(byte) {
+            Some(partial) => partial & (u8::MAX << (token_id % 8)) != 0 || bits[byte + 1..].iter().any(|bits| *bits != 0),
+            None => false,
+        }
+    }
+
+    /// Lists up to `limit` unminted token ids in `start..=max_supply`.
+    pub fn free_token_ids(bits: &[u8], max_supply: u64, start: u64, limit: usize) -> Vec<u64> {
+        (start.max(1)..=max_supply)
+            .filter(|token_id| matches!(Self::is_minted(bits, *token_id), Ok(false)))
+            .take(limit)
+            .collect()
+    }
+}
+
+/// Header of the pool of token ids not yet drawn by `mint_random`, followed by
+/// one little-endian `u32` slot per token id. A zero slot at index `i` stands
+/// for token id `i + 1`, so the pool needs no initialization beyond zeroing.
+#[account(zero_copy)]
+pub struct RemainingTokenIds {
+    pub nft_pda: Pubkey,
+    pub remaining: u64,
+}
+
+impl RemainingTokenIds {
+    pub const SLOTS_OFFSET: usize = 8 + std::mem::size_of::<RemainingTokenIds>();
+
+    pub fn space(capacity: u64) -> usize {
+        Self::SLOTS_OFFSET + capacity as usize * 4
+    }
+
+    /// Checks that the pool belongs to the collection and has been grown to
+    /// one slot per token id.
+    pub fn check(remaining_token_ids: &AccountLoader<RemainingTokenIds>, nft_pda: &Account<NftPda>) -> Result<()> {
+        if remaining_token_ids.load()?.nft_pda != nft_pda.key()
+            || remaining_token_ids.to_account_info().data_len() < Self::space(nft_pda.max_supply)
+        {
+            return Err(error!(ErrorCode::InvalidRemainingTokenIds));
+        }
+
+        Ok(())
+    }
+
+    fn slot(slots: &[u8], index: usize) -> u64 {
+        let offset = index * 4;
+        match u32::from_le_bytes(slots[offset..offset + 4].try_into().unwrap()) {
+            0 => index as u64 + 1,
+            token_id => token_id as u64,
+        }
+    }
+
+    /// Swap-and-pop: takes the token id at `seed % remaining` and moves the
+    /// last remaining token id into its slot.
+    pub fn draw(slots: &mut [u8], remaining: u64, seed: u64) -> u64 {
+        let index = (seed % remaining) as usize;
+        let last = remaining as usize - 1;
+        let token_id = Self::slot(slots, index);
+        let last_token_id = Self::slot(slots, last) as u32;
+
+        slots[index * 4..index * 4 + 4].copy_from_slice(&last_token_id.to_le_bytes());
+
+        token_id
+    }
+}
+
 #[error_code]
 pub enum ErrorCode {
     #[msg("You are not authorized to perform this action.")]
@@ -802,4 +3766,546 @@ pub enum ErrorCode {
     InvalidCollectionMint,
     #[msg("Invalid token id.")]
     InvalidTokenId,
+    #[msg("Invalid max supply.")]
+    InvalidMaxSupply,
+    #[msg("All tokens have been minted.")]
+    SoldOut,
+    #[msg("Token id has already been minted.")]
+    TokenAlreadyMinted,
+    #[msg("Minting has already started.")]
+    MintAlreadyStarted,
+    #[msg("Instruction is not available in the current mint mode.")]
+    InvalidMintMode,
+    #[msg("Invalid token record.")]
+    InvalidTokenRecord,
+    #[msg("Random mints must be called directly, not through CPI.")]
+    CpiNotAllowed,
+    #[msg("Random mint tickets must be revealed in order.")]
+    RevealOutOfOrder,
+    #[msg("Random mint ticket can't be revealed in the slot it was bought.")]
+    RevealTooEarly,
+    #[msg("Invalid remaining token ids account.")]
+    InvalidRemainingTokenIds,
+    #[msg("Withdrawal would leave the account below the rent-exempt minimum.")]
+    InsufficientFunds,
+    #[msg("Payee shares must sum to 10,000 basis points.")]
+    InvalidRevenueSplit,
+    #[msg("Payee accounts do not match the revenue split.")]
+    InvalidPayee,
+    #[msg("Proceeds are paid out through the revenue split.")]
+    RevenueSplitActive,
+    #[msg("Invalid treasury token account.")]
+    InvalidTreasury,
+    #[msg("Invalid sale phase.")]
+    InvalidSalePhase,
+    #[msg("Sale phase has already started.")]
+    SalePhaseStarted,
+    #[msg("No sale phase is active.")]
+    NoActiveSalePhase,
+    #[msg("Wallet has reached its mint limit.")]
+    WalletMintLimitReached,
+    #[msg("Payer is not on the allowlist.")]
+    NotAllowlisted,
+    #[msg("Payer does not hold an NFT from the gate collection.")]
+    NotTokenHolder,
+    #[msg("Invalid mint voucher.")]
+    InvalidVoucher,
+    #[msg("Mint voucher has already been used.")]
+    VoucherAlreadyUsed,
+    #[msg("Invalid pricing mode.")]
+    InvalidPricingMode,
+    #[msg("Price is higher than the buyer's maximum.")]
+    PriceExceeded,
+    #[msg("Price calculation overflowed.")]
+    PriceOverflow,
+    #[msg("Sale is paused.")]
+    SalePaused,
+    #[msg("Invalid price override.")]
+    InvalidPriceOverride,
+    #[msg("Action requires multisig approval.")]
+    MultisigRequired,
+    #[msg("Invalid multisig.")]
+    InvalidMultisig,
+    #[msg("Signer is not a multisig member.")]
+    NotMultisigSigner,
+    #[msg("Proposal has expired.")]
+    ProposalExpired,
+    #[msg("Proposal already approved by this signer.")]
+    ProposalAlreadyApproved,
+    #[msg("Proposal does not have enough approvals.")]
+    ProposalNotApproved,
+    #[msg("Proposal already executed.")]
+    ProposalExecuted,
+    #[msg("Invalid proposal.")]
+    InvalidProposal,
+    #[msg("Invalid redemption token.")]
+    InvalidRedemptionToken,
+    #[msg("Invalid referral share.")]
+    InvalidReferralShare,
+    #[msg("Invalid referrer.")]
+    InvalidReferrer,
+    #[msg("Referrals are unavailable for this sale.")]
+    ReferralUnavailable,
+    #[msg("Invalid soft cap.")]
+    InvalidSoftCap,
+    #[msg("Not available while a soft cap is set.")]
+    SoftCapActive,
+    #[msg("Soft cap was not reached by the deadline.")]
+    SoftCapFailed,
+    #[msg("Soft cap has not been reached.")]
+    SoftCapNotReached,
+    #[msg("Refunds are not available.")]
+    RefundUnavailable,
+    #[msg("Invalid escrow vault.")]
+    InvalidVault,
+    #[msg("Token is not a member of the collection.")]
+    NotCollectionMember,
+    #[msg("Invalid auction parameters.")]
+    InvalidAuction,
+    #[msg("Auction has ended.")]
+    AuctionEnded,
+    #[msg("Auction has not ended yet.")]
+    AuctionNotEnded,
+    #[msg("Auction already has bids.")]
+    AuctionHasBids,
+    #[msg("Auction has already been settled.")]
+    AuctionSettled,
+    #[msg("Bid is too low.")]
+    BidTooLow,
+    #[msg("Account does not match the highest bidder.")]
+    InvalidBidder,
+    #[msg("An auction is still open.")]
+    AuctionOpen,
+    #[msg("Account is not a legacy nft pda.")]
+    InvalidMigration,
+    #[msg("Auction can still be settled.")]
+    SettlementWindowOpen,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn nft_pda() -> NftPda {
+        NftPda {
+            creator: Pubkey::default(),
+            pending_creator: None,
+            price_admin: Pubkey::default(),
+            metadata_admin: Pubkey::default(),
+            treasury_admin: Pubkey::default(),
+            collection_admin: Pubkey::default(),
+            name: String::new(),
+            symbol: String::new(),
+            base_token_uri: String::new(),
+            price_lamports: 100,
+            max_supply: 100,
+            minted_count: 0,
+            mint_mode: MintMode::Explicit,
+            next_token_id: 1,
+            payment_mint: None,
+            price_tokens: 0,
+            sale_phases: Vec::new(),
+            next_phase_id: 0,
+            allowlist_root: None,
+            gate_collection: None,
+            pricing_mode: PricingMode::Fixed,
+            soft_cap: 0,
+            soft_cap_deadline: 0,
+            raised: 0,
+            voucher_signer: None,
+            paused: false,
+            referral_bps: 0,
+            price_overrides: Vec::new(),
+            redemption: None,
+            multisig_enabled: false,
+            next_random_ticket: 0,
+            next_reveal_ticket: 0,
+            open_auctions: 0,
+            bump: 0,
+        }
+    }
+
+    fn sale_phase(id: u8, start_ts: i64, end_ts: i64) -> SalePhase {
+        SalePhase { id, start_ts, end_ts, price: 0, per_wallet_cap: 0 }
+    }
+
+    #[test]
+    fn mint_bitmap_tracks_token_ids() {
+        let mut bits = vec![0u8; 2];
+
+        assert!(!MintBitmap::is_minted(&bits, 1).unwrap());
+        MintBitmap::set_minted(&mut bits, 1).unwrap();
+        MintBitmap::set_minted(&mut bits, 9).unwrap();
+        MintBitmap::set_minted(&mut bits, 16).unwrap();
+        assert!(MintBitmap::is_minted(&bits, 1).unwrap());
+        assert!(MintBitmap::is_minted(&bits, 9).unwrap());
+        assert!(!MintBitmap::is_minted(&bits, 8).unwrap());
+
+        MintBitmap::clear_minted(&mut bits, 9).unwrap();
+        assert!(!MintBitmap::is_minted(&bits, 9).unwrap());
+        assert!(MintBitmap::is_minted(&bits, 16).unwrap());
+
+        assert_eq!(MintBitmap::free_token_ids(&bits, 16, 0, 3), vec![2, 3, 4]);
+        assert_eq!(MintBitmap::free_token_ids(&bits, 16, 15, 10), vec![15]);
+        assert_eq!(MintBitmap::free_token_ids(&bits, 100, 16, 10), Vec::<u64>::new());
+    }
+
+    #[test]
+    fn mint_bitmap_rejects_token_ids_outside_it() {
+        let mut bits = vec![0u8; 2];
+
+        assert!(MintBitmap::is_minted(&bits, 0).is_err());
+        assert!(MintBitmap::is_minted(&bits, 17).is_err());
+        assert!(MintBitmap::set_minted(&mut bits, 0).is_err());
+        assert!(MintBitmap::set_minted(&mut bits, u64::MAX).is_err());
+        assert!(MintBitmap::clear_minted(&mut bits, 17).is_err());
+        assert_eq!(bits, vec![0, 0]);
+    }
+
+    #[test]
+    fn mint_bitmap_finds_minted_ids_above() {
+        let mut bits = vec![0u8; 3];
+
+        assert!(!MintBitmap::any_minted_above(&bits, 0));
+
+        MintBitmap::set_minted(&mut bits, 8).unwrap();
+        assert!(MintBitmap::any_minted_above(&bits, 7));
+        assert!(!MintBitmap::any_minted_above(&bits, 8));
+
+        MintBitmap::set_minted(&mut bits, 17).unwrap();
+        assert!(MintBitmap::any_minted_above(&bits, 16));
+        assert!(MintBitmap::any_minted_above(&bits, 9));
+        assert!(!MintBitmap::any_minted_above(&bits, 17));
+        assert!(!MintBitmap::any_minted_above(&bits, 24));
+        assert!(!MintBitmap::any_minted_above(&bits, u64::MAX));
+    }
+
+    #[test]
+    fn draw_yields_each_token_id_once() {
+        let supply = 50u64;
+        let mut slots = vec![0u8; supply as usize * 4];
+        let mut drawn: Vec<u64> = (0..supply)
+            .map(|n| RemainingTokenIds::draw(&mut slots, supply - n, n.wrapping_mul(0x9e37_79b9_7f4a_7c15)))
+            .collect();
+
+        drawn.sort_unstable();
+        assert_eq!(drawn, (1..=supply).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn draw_moves_last_token_id_into_the_gap() {
+        let mut slots = vec![0u8; 4 * 4];
+
+        assert_eq!(RemainingTokenIds::draw(&mut slots, 4, 1), 2);
+        assert_eq!(RemainingTokenIds::draw(&mut slots, 3, 1), 4);
+        assert_eq!(RemainingTokenIds::draw(&mut slots, 2, 1), 3);
+        assert_eq!(RemainingTokenIds::draw(&mut slots, 1, 7), 1);
+    }
+
+    #[test]
+    fn finds_slot_hashes() {
+        let mut data = 3u64.to_le_bytes().to_vec();
+        for slot in [30u64, 20, 10] {
+            data.extend_from_slice(&slot.to_le_bytes());
+            data.extend_from_slice(&[slot as u8; 32]);
+        }
+
+        assert_eq!(find_slot_hash(&data, 20), Some(&[20u8; 32][..]));
+        assert_eq!(find_slot_hash(&data, 10), Some(&[10u8; 32][..]));
+        assert_eq!(find_slot_hash(&data, 15), None);
+        assert_eq!(find_slot_hash(&data[..4], 30), None);
+
+        // entries past the declared length are ignored
+        data[..8].copy_from_slice(&1u64.to_le_bytes());
+        assert_eq!(find_slot_hash(&data, 20), None);
+    }
+
+    #[test]
+    fn sale_phase_must_be_in_the_future() {
+        let nft_pda = nft_pda();
+
+        assert!(nft_pda.validate_sale_phase(&sale_phase(0, 101, 200), 100).is_ok());
+        assert!(nft_pda.validate_sale_phase(&sale_phase(0, 100, 200), 100).is_err());
+        assert!(nft_pda.validate_sale_phase(&sale_phase(0, 150, 150), 100).is_err());
+        assert!(nft_pda.validate_sale_phase(&sale_phase(0, 150, 140), 100).is_err());
+    }
+
+    #[test]
+    fn sale_phases_must_not_overlap() {
+        let mut nft_pda = nft_pda();
+        nft_pda.sale_phases = vec![sale_phase(0, 200, 300)];
+
+        // touching ends don't overlap
+        assert!(nft_pda.validate_sale_phase(&sale_phase(1, 100, 200), 0).is_ok());
+        assert!(nft_pda.validate_sale_phase(&sale_phase(1, 300, 400), 0).is_ok());
+
+        assert!(nft_pda.validate_sale_phase(&sale_phase(1, 100, 201), 0).is_err());
+        assert!(nft_pda.validate_sale_phase(&sale_phase(1, 299, 400), 0).is_err());
+        assert!(nft_pda.validate_sale_phase(&sale_phase(1, 210, 290), 0).is_err());
+        assert!(nft_pda.validate_sale_phase(&sale_phase(1, 100, 400), 0).is_err());
+
+        // an edited phase may overlap its own old times
+        assert!(nft_pda.validate_sale_phase(&sale_phase(0, 250, 350), 0).is_ok());
+    }
+
+    #[test]
+    fn only_pending_sale_phases_can_change() {
+        let mut nft_pda = nft_pda();
+        nft_pda.sale_phases = vec![sale_phase(3, 100, 200), sale_phase(5, 300, 400)];
+
+        assert_eq!(nft_pda.pending_sale_phase_index(5, 299).unwrap(), 1);
+        assert_eq!(nft_pda.pending_sale_phase_index(3, 99).unwrap(), 0);
+        assert!(nft_pda.pending_sale_phase_index(3, 100).is_err());
+        assert!(nft_pda.pending_sale_phase_index(5, 350).is_err());
+        assert!(nft_pda.pending_sale_phase_index(4, 0).is_err());
+    }
+
+    #[test]
+    fn merkle_proof_is_independent_of_pair_order() {
+        let leaves = [[1u8; 32], [2u8; 32], [3u8; 32], [4u8; 32]];
+        let node = |a: [u8; 32], b: [u8; 32]| if a <= b {
+            keccak::hashv(&[&a, &b]).0
+        } else {
+            keccak::hashv(&[&b, &a]).0
+        };
+        let left = node(leaves[1], leaves[0]);
+        let right = node(leaves[2], leaves[3]);
+        let root = node(right, left);
+
+        assert!(verify_merkle_proof(&[leaves[1], right], root, leaves[0]));
+        assert!(verify_merkle_proof(&[leaves[0], right], root, leaves[1]));
+        assert!(verify_merkle_proof(&[leaves[3], left], root, leaves[2]));
+        assert!(verify_merkle_proof(&[leaves[2], left], root, leaves[3]));
+    }
+
+    #[test]
+    fn merkle_proof_rejects_wrong_proofs() {
+        let leaves = [[1u8; 32], [2u8; 32], [3u8; 32]];
+        let root = keccak::hashv(&[&leaves[0], &leaves[1]]).0;
+
+        assert!(verify_merkle_proof(&[leaves[1]], root, leaves[0]));
+        assert!(!verify_merkle_proof(&[leaves[2]], root, leaves[0]));
+        assert!(!verify_merkle_proof(&[leaves[1], leaves[1]], root, leaves[0]));
+        assert!(!verify_merkle_proof(&[], root, leaves[0]));
+        assert!(verify_merkle_proof(&[], leaves[0], leaves[0]));
+    }
+
+    #[test]
+    fn fixed_price_ignores_supply_and_time() {
+        assert_eq!(PricingMode::Fixed.price(5, u64::MAX, i64::MAX).unwrap(), 5);
+    }
+
+    #[test]
+    fn dutch_auction_decays_to_floor() {
+        let mode = PricingMode::DutchAuction {
+            start_price: 1_000,
+            floor_price: 400,
+            start_ts: 100,
+            decay_amount: 100,
+            decay_interval: 60,
+        };
+
+        assert_eq!(mode.price(0, 0, 0).unwrap(), 1_000);
+        assert_eq!(mode.price(0, 0, 159).unwrap(), 1_000);
+        assert_eq!(mode.price(0, 0, 160).unwrap(), 900);
+        assert_eq!(mode.price(0, 0, 100 + 6 * 60).unwrap(), 400);
+        assert_eq!(mode.price(0, 0, i64::MAX).unwrap(), 400);
+    }
+
+    #[test]
+    fn dutch_auction_saturates_large_decay() {
+        let mode = PricingMode::DutchAuction {
+            start_price: 1_000,
+            floor_price: 1,
+            start_ts: i64::MIN,
+            decay_amount: u64::MAX,
+            decay_interval: 1,
+        };
+
+        assert_eq!(mode.price(0, 0, i64::MAX).unwrap(), 1);
+    }
+
+    #[test]
+    fn dutch_auction_validation() {
+        let mode = |floor_price, decay_interval| PricingMode::DutchAuction {
+            start_price: 1_000,
+            floor_price,
+            start_ts: 0,
+            decay_amount: 1,
+            decay_interval,
+        };
+
+        assert!(mode(1_000, 1).validate().is_ok());
+        assert!(mode(1_001, 1).validate().is_err());
+        assert!(mode(0, 0).validate().is_err());
+        assert!(mode(0, -1).validate().is_err());
+    }
+
+    #[test]
+    fn soft_cap_fails_only_after_deadline_when_short() {
+        let mut nft_pda = nft_pda();
+        nft_pda.soft_cap = 1_000;
+        nft_pda.soft_cap_deadline = 500;
+        nft_pda.raised = 999;
+
+        assert!(!nft_pda.soft_cap_failed(499));
+        assert!(nft_pda.soft_cap_failed(500));
+
+        nft_pda.raised = 1_000;
+        assert!(!nft_pda.soft_cap_failed(500));
+        assert!(!nft_pda.soft_cap_failed(i64::MAX));
+    }
+
+    #[test]
+    fn record_mint_counts_raised_only_under_soft_cap() {
+        let mut nft_pda = nft_pda();
+
+        nft_pda.record_mint(100);
+        assert_eq!((nft_pda.minted_count, nft_pda.raised), (1, 0));
+
+        nft_pda.soft_cap = 1_000;
+        nft_pda.record_mint(100);
+        assert_eq!((nft_pda.minted_count, nft_pda.raised), (2, 100));
+    }
+
+    #[test]
+    fn linear_curve() {
+        let mode = PricingMode::LinearCurve { base_price: 100, increment: 10 };
+
+        assert_eq!(mode.price(0, 0, 0).unwrap(), 100);
+        assert_eq!(mode.price(0, 5, 0).unwrap(), 150);
+    }
+
+    #[test]
+    fn linear_curve_overflow() {
+        let mode = PricingMode::LinearCurve { base_price: 1, increment: u64::MAX };
+
+        assert_eq!(mode.price(0, 0, 0).unwrap(), 1);
+        assert!(mode.price(0, 1, 0).is_err());
+        assert!(mode.price(0, 2, 0).is_err());
+    }
+
+    #[test]
+    fn exponential_curve() {
+        let mode = PricingMode::ExponentialCurve { base_price: 1_000, growth_bps: 10_000 };
+
+        assert_eq!(mode.price(0, 0, 0).unwrap(), 1_000);
+        assert_eq!(mode.price(0, 1, 0).unwrap(), 2_000);
+        assert_eq!(mode.price(0, 10, 0).unwrap(), 1_024_000);
+
+        let mode = PricingMode::ExponentialCurve { base_price: 10_000, growth_bps: 500 };
+
+        assert_eq!(mode.price(0, 2, 0).unwrap(), 11_025);
+    }
+
+    #[test]
+    fn exponential_curve_without_growth_stays_flat() {
+        let mode = PricingMode::ExponentialCurve { base_price: 1_000, growth_bps: 0 };
+
+        assert_eq!(mode.price(0, u64::MAX, 0).unwrap(), 1_000);
+    }
+
+    #[test]
+    fn exponential_curve_overflow() {
+        let mode = PricingMode::ExponentialCurve { base_price: u64::MAX, growth_bps: 10_000 };
+
+        assert!(mode.price(0, 1, 0).is_err());
+        assert!(PricingMode::growth_factor(10_000, 200).is_none());
+        assert!(PricingMode::growth_factor(u16::MAX, u64::MAX).is_none());
+    }
+
+    fn ed25519_data(public_key: &[u8; 32], message: &[u8]) -> Vec<u8> {
+        let public_key_offset = 16u16;
+        let signature_offset = public_key_offset + 32;
+        let message_data_offset = signature_offset + 64;
+
+        let mut data = vec![1, 0];
+        for value in [
+            signature_offset,
+            u16::MAX,
+            public_key_offset,
+            u16::MAX,
+            message_data_offset,
+            message.len() as u16,
+            u16::MAX,
+        ] {
+            data.extend_from_slice(&value.to_le_bytes());
+        }
+        data.extend_from_slice(public_key);
+        data.extend_from_slice(&[0; 64]);
+        data.extend_from_slice(message);
+        data
+    }
+
+    #[test]
+    fn parses_inline_ed25519_instruction() {
+        let data = ed25519_data(&[7; 32], b"voucher");
+
+        assert_eq!(parse_ed25519_instruction(&data), Some((&[7u8; 32][..], &b"voucher"[..])));
+    }
+
+    #[test]
+    fn rejects_malformed_ed25519_instruction() {
+        let data = ed25519_data(&[7; 32], b"voucher");
+
+        assert_eq!(parse_ed25519_instruction(&[]), None);
+        assert_eq!(parse_ed25519_instruction(&data[..15]), None);
+        assert_eq!(parse_ed25519_instruction(&data[..data.len() - 1]), None);
+
+        for num_signatures in [0, 2] {
+            let mut data = data.clone();
+            data[0] = num_signatures;
+            assert_eq!(parse_ed25519_instruction(&data), None);
+        }
+
+        // any of the signature, public key or message read from another instruction
+        for offset in [4, 8, 14] {
+            let mut data = data.clone();
+            data[offset..offset + 2].copy_from_slice(&0u16.to_le_bytes());
+            assert_eq!(parse_ed25519_instruction(&data), None);
+        }
+
+        // public key and message running past the end
+        for offset in [6, 10, 12] {
+            let mut data = data.clone();
+            data[offset..offset + 2].copy_from_slice(&u16::MAX.to_le_bytes());
+            assert_eq!(parse_ed25519_instruction(&data), None);
+        }
+    }
+
+    #[test]
+    fn price_overrides_must_not_overlap() {
+        let price_override = |start_id, end_id| PriceOverride { start_id, end_id, price: 0 };
+
+        assert!(NftPda::validate_price_overrides(&[]).is_ok());
+        assert!(NftPda::validate_price_overrides(&[price_override(1, 1), price_override(2, 10)]).is_ok());
+        assert!(NftPda::validate_price_overrides(&[price_override(11, 20), price_override(1, 10)]).is_ok());
+
+        assert!(NftPda::validate_price_overrides(&[price_override(0, 10)]).is_err());
+        assert!(NftPda::validate_price_overrides(&[price_override(10, 9)]).is_err());
+        assert!(NftPda::validate_price_overrides(&[price_override(1, 10), price_override(10, 20)]).is_err());
+        assert!(NftPda::validate_price_overrides(&[price_override(5, 6), price_override(1, 10)]).is_err());
+        assert!(NftPda::validate_price_overrides(&[price_override(1, 10), price_override(20, 30), price_override(25, 25)]).is_err());
+
+        let too_many: Vec<_> = (1..=NftPda::MAX_PRICE_OVERRIDES as u64 + 1)
+            .map(|token_id| price_override(token_id, token_id))
+            .collect();
+        assert!(NftPda::validate_price_overrides(&too_many[1..]).is_ok());
+        assert!(NftPda::validate_price_overrides(&too_many).is_err());
+    }
+
+    #[test]
+    fn token_price_uses_covering_override() {
+        let mut nft_pda = nft_pda();
+        nft_pda.price_overrides = vec![
+            PriceOverride { start_id: 1, end_id: 1, price: 500 },
+            PriceOverride { start_id: 10, end_id: 20, price: 50 },
+        ];
+
+        assert_eq!(nft_pda.token_price(1, 100), 500);
+        assert_eq!(nft_pda.token_price(2, 100), 100);
+        assert_eq!(nft_pda.token_price(10, 100), 50);
+        assert_eq!(nft_pda.token_price(20, 100), 50);
+        assert_eq!(nft_pda.token_price(21, 100), 100);
+    }
 }
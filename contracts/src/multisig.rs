@@ -0,0 +1,388 @@
+use {
+    anchor_lang::prelude::*,
+    crate::mint::{
+        update_metadata,
+        ErrorCode,
+        NftPda,
+    },
+};
+
+pub fn create_multisig(
+    ctx: Context<CreateMultisig>,
+    signers: Vec<Pubkey>,
+    threshold: u8,
+    proposal_lifetime: i64,
+) -> Result<()> {
+    let nft_pda = &ctx.accounts.nft_pda;
+
+    if &nft_pda.creator != ctx.accounts.authority.key {
+        return Err(error!(ErrorCode::Unauthorized));
+    }
+
+    // once enabled, the multisig can only be turned off by its own proposal
+    if nft_pda.multisig_enabled {
+        return Err(error!(ErrorCode::MultisigRequired));
+    }
+
+    if signers.is_empty() || signers.len() > Multisig::MAX_SIGNERS {
+        return Err(error!(ErrorCode::InvalidMultisig));
+    }
+
+    let has_duplicates = signers.iter().enumerate().any(|(index, signer)| signers[..index].contains(signer));
+    if has_duplicates {
+        return Err(error!(ErrorCode::InvalidMultisig));
+    }
+
+    if threshold == 0 || threshold as usize > signers.len() || proposal_lifetime <= 0 {
+        return Err(error!(ErrorCode::InvalidMultisig));
+    }
+
+    let multisig = &mut ctx.accounts.multisig;
+
+    multisig.signers = signers;
+    multisig.threshold = threshold;
+    multisig.proposal_lifetime = proposal_lifetime;
+    multisig.bump = *ctx.bumps.get("multisig").unwrap();
+
+    ctx.accounts.nft_pda.multisig_enabled = true;
+
+    Ok(())
+}
+
+pub fn propose_action(ctx: Context<ProposeAction>, action: AdminAction) -> Result<()> {
+    if !ctx.accounts.nft_pda.multisig_enabled {
+        return Err(error!(ErrorCode::InvalidMultisig));
+    }
+
+    let multisig = &mut ctx.accounts.multisig;
+    let proposer = ctx.accounts.proposer.key();
+
+    if !multisig.signers.contains(&proposer) {
+        return Err(error!(ErrorCode::NotMultisigSigner));
+    }
+
+    action.validate()?;
+
+    let proposal = &mut ctx.accounts.proposal;
+
+    proposal.id = multisig.next_proposal_id;
+    proposal.proposer = proposer;
+    proposal.action = action;
+    // proposing counts as the proposer's approval
+    proposal.approvals = vec![proposer];
+    proposal.expires_at = Clock::get()?.unix_timestamp + multisig.proposal_lifetime;
+    proposal.executed = false;
+    proposal.bump = *ctx.bumps.get("proposal").unwrap();
+
+    multisig.next_proposal_id += 1;
+
+    Ok(())
+}
+
+pub fn approve_action(ctx: Context<ApproveAction>, _proposal_id: u64) -> Result<()> {
+    let approver = ctx.accounts.approver.key();
+
+    if !ctx.accounts.multisig.signers.contains(&approver) {
+        return Err(error!(ErrorCode::NotMultisigSigner));
+    }
+
+    let proposal = &mut ctx.accounts.proposal;
+
+    proposal.check_pending(Clock::get()?.unix_timestamp)?;
+
+    if proposal.approvals.contains(&approver) {
+        return Err(error!(ErrorCode::ProposalAlreadyApproved));
+    }
+
+    proposal.approvals.push(approver);
+
+    Ok(())
+}
+
+/// For an `UpdateMetadataAccount` action, takes the metadata account as the
+/// only remaining account.
+pub fn execute_action<'info>(
+    ctx: Context<'_, '_, '_, 'info, ExecuteAction<'info>>,
+    _proposal_id: u64,
+) -> Result<()> {
+    let multisig = &ctx.accounts.multisig;
+
+    if !ctx.accounts.nft_pda.multisig_enabled {
+        return Err(error!(ErrorCode::InvalidMultisig));
+    }
+
+    if !multisig.signers.contains(ctx.accounts.executor.key) {
+        return Err(error!(ErrorCode::NotMultisigSigner));
+    }
+
+    let proposal = &ctx.accounts.proposal;
+
+    proposal.check_pending(Clock::get()?.unix_timestamp)?;
+
+    // approvals from signers removed since the proposal was made don't count
+    let approvals = proposal.approvals
+        .iter()
+        .filter(|approver| multisig.signers.contains(approver))
+        .count();
+    if approvals < multisig.threshold as usize {
+        return Err(error!(ErrorCode::ProposalNotApproved));
+    }
+
+    match proposal.action.clone() {
+        AdminAction::SetPrice { price_lamports } => {
+            ctx.accounts.nft_pda.price_lamports = price_lamports;
+        }
+        AdminAction::SetPriceTokens { price_tokens } => {
+            ctx.accounts.nft_pda.price_tokens = price_tokens;
+        }
+        AdminAction::SetMetadata { name, symbol, base_token_uri } => {
+            let nft_pda = &mut ctx.accounts.nft_pda;
+
+            nft_pda.name = name;
+            nft_pda.symbol = symbol;
+            nft_pda.base_token_uri = base_token_uri;
+        }
+        AdminAction::UpdateMetadataAccount { name, symbol, uri } => {
+            let metadata = next_account_info(&mut ctx.remaining_accounts.iter())?;
+
+            update_metadata(
+                &ctx.accounts.nft_pda,
+                &ctx.accounts.nft_manager.to_account_info(),
+                metadata,
+                name,
+                symbol,
+                uri,
+            )?;
+        }
+        AdminAction::DisableMultisig => {
+            ctx.accounts.nft_pda.multisig_enabled = false;
+        }
+    }
+
+    ctx.accounts.proposal.executed = true;
+
+    Ok(())
+}
+
+#[derive(Accounts)]
+pub struct CreateMultisig<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    // space: 8 discriminator
+    // + 4 signers length + 10 * 32 signers
+    // + 1 threshold
+    // + 8 proposal_lifetime
+    // + 8 next_proposal_id
+    // + 1 bump
+    #[account(
+        init_if_needed,
+        payer = authority,
+        space = 350,
+        seeds = [b"multisig".as_ref(), nft_manager.to_account_info().key.as_ref()],
+        bump,
+    )]
+    pub multisig: Account<'info, Multisig>,
+    #[account(mut)]
+    pub authority: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+pub struct ProposeAction<'info> {
+    #[account(seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(mut, seeds = [b"multisig".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub multisig: Account<'info, Multisig>,
+    // space: 8 discriminator
+    // + 8 id
+    // + 32 proposer
+    // + 1 action variant + 4 + 100 name + 4 + 100 symbol + 4 + 200 uri
+    // + 4 approvals length + 10 * 32 approvals
+    // + 8 expires_at
+    // + 1 executed
+    // + 1 bump
+    #[account(
+        init,
+        payer = proposer,
+        space = 795,
+        seeds = [b"proposal".as_ref(), nft_manager.to_account_info().key.as_ref(), multisig.next_proposal_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub proposal: Account<'info, Proposal>,
+    #[account(mut)]
+    pub proposer: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    pub system_program: Program<'info, System>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ApproveAction<'info> {
+    #[account(seeds = [b"multisig".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        mut,
+        seeds = [b"proposal".as_ref(), nft_manager.to_account_info().key.as_ref(), proposal_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub proposal: Account<'info, Proposal>,
+    pub approver: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+}
+
+#[derive(Accounts)]
+#[instruction(proposal_id: u64)]
+pub struct ExecuteAction<'info> {
+    #[account(mut, seeds = [b"nft_pda".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub nft_pda: Account<'info, NftPda>,
+    #[account(seeds = [b"multisig".as_ref(), nft_manager.to_account_info().key.as_ref()], bump)]
+    pub multisig: Account<'info, Multisig>,
+    #[account(
+        mut,
+        seeds = [b"proposal".as_ref(), nft_manager.to_account_info().key.as_ref(), proposal_id.to_le_bytes().as_ref()],
+        bump,
+    )]
+    pub proposal: Account<'info, Proposal>,
+    pub executor: Signer<'info>,
+    /// CHECK: Only used to derive the PDAs
+    pub nft_manager: UncheckedAccount<'info>,
+    /// CHECK: Metaplex will check this
+    pub token_metadata_program: UncheckedAccount<'info>,
+}
+
+/// Signers allowed to propose and approve admin actions while the
+/// collection's multisig is enabled.
+#[account]
+pub struct Multisig {
+    pub signers: Vec<Pubkey>,
+    pub threshold: u8,
+    /// Seconds a proposal stays open for approval and execution.
+    pub proposal_lifetime: i64,
+    pub next_proposal_id: u64,
+    pub bump: u8,
+}
+
+impl Multisig {
+    pub const MAX_SIGNERS: usize = 10;
+}
+
+#[account]
+pub struct Proposal {
+    pub id: u64,
+    pub proposer: Pubkey,
+    pub action: AdminAction,
+    pub approvals: Vec<Pubkey>,
+    pub expires_at: i64,
+    pub executed: bool,
+    pub bump: u8,
+}
+
+impl Proposal {
+    fn check_pending(&self, now: i64) -> Result<()> {
+        if self.executed {
+            return Err(error!(ErrorCode::ProposalExecuted));
+        }
+
+        if now >= self.expires_at {
+            return Err(error!(ErrorCode::ProposalExpired));
+        }
+
+        Ok(())
+    }
+}
+
+/// Admin instructions that need multisig approval while it is enabled.
+#[derive(AnchorSerialize, AnchorDeserialize, Clone)]
+pub enum AdminAction {
+    SetPrice { price_lamports: u64 },
+    /// Price in units of the payment mint.
+    SetPriceTokens { price_tokens: u64 },
+    SetMetadata { name: String, symbol: String, base_token_uri: String },
+    UpdateMetadataAccount { name: String, symbol: String, uri: String },
+    DisableMultisig,
+}
+
+impl AdminAction {
+    pub const MAX_NAME_LEN: usize = 100;
+    pub const MAX_SYMBOL_LEN: usize = 100;
+    pub const MAX_URI_LEN: usize = 200;
+
+    /// Checks that the strings fit the space reserved for them in `NftPda`
+    /// and `Proposal`.
+    pub fn validate(&self) -> Result<()> {
+        let fits = match self {
+            AdminAction::SetMetadata { name, symbol, base_token_uri } => {
+                name.len() <= Self::MAX_NAME_LEN
+                    && symbol.len() <= Self::MAX_SYMBOL_LEN
+                    && base_token_uri.len() <= Self::MAX_URI_LEN
+            }
+            AdminAction::UpdateMetadataAccount { name, symbol, uri } => {
+                name.len() <= Self::MAX_NAME_LEN
+                    && symbol.len() <= Self::MAX_SYMBOL_LEN
+                    && uri.len() <= Self::MAX_URI_LEN
+            }
+            AdminAction::SetPrice { .. }
+            | AdminAction::SetPriceTokens { .. }
+            | AdminAction::DisableMultisig => true,
+        };
+
+        if !fits {
+            return Err(error!(ErrorCode::InvalidProposal));
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set_metadata(name_len: usize, symbol_len: usize, uri_len: usize) -> AdminAction {
+        AdminAction::SetMetadata {
+            name: "n".repeat(name_len),
+            symbol: "s".repeat(symbol_len),
+            base_token_uri: "u".repeat(uri_len),
+        }
+    }
+
+    fn update_metadata_account(name_len: usize, symbol_len: usize, uri_len: usize) -> AdminAction {
+        AdminAction::UpdateMetadataAccount {
+            name: "n".repeat(name_len),
+            symbol: "s".repeat(symbol_len),
+            uri: "u".repeat(uri_len),
+        }
+    }
+
+    #[test]
+    fn metadata_actions_fit_reserved_space() {
+        let (name, symbol, uri) = (AdminAction::MAX_NAME_LEN, AdminAction::MAX_SYMBOL_LEN, AdminAction::MAX_URI_LEN);
+
+        for action in [set_metadata, update_metadata_account] {
+            assert!(action(0, 0, 0).validate().is_ok());
+            assert!(action(name, symbol, uri).validate().is_ok());
+            assert!(action(name + 1, symbol, uri).validate().is_err());
+            assert!(action(name, symbol + 1, uri).validate().is_err());
+            assert!(action(name, symbol, uri + 1).validate().is_err());
+        }
+    }
+
+    #[test]
+    fn non_string_actions_are_always_valid() {
+        assert!(AdminAction::SetPrice { price_lamports: u64::MAX }.validate().is_ok());
+        assert!(AdminAction::SetPriceTokens { price_tokens: u64::MAX }.validate().is_ok());
+        assert!(AdminAction::DisableMultisig.validate().is_ok());
+    }
+
+    #[test]
+    fn largest_action_fits_proposal_space() {
+        let action = update_metadata_account(AdminAction::MAX_NAME_LEN, AdminAction::MAX_SYMBOL_LEN, AdminAction::MAX_URI_LEN);
+
+        // 1 variant + 4 + 100 name + 4 + 100 symbol + 4 + 200 uri
+        assert_eq!(action.try_to_vec().unwrap().len(), 413);
+    }
+}